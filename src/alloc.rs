@@ -0,0 +1,256 @@
+use anyhow::Result;
+use anyhow::anyhow;
+
+use crate::check::{self, Availability};
+use crate::import::{self, ImportLine};
+use crate::types::Card;
+
+/// Allocates copies of `collection` to `deck` for every card in `cards`, as
+/// `check` would pick them: the same printing first, other printings second.
+/// Replaces whatever was allocated to `deck` before.
+pub fn allocate_deck(collection: &mut [Card], deck: &str, cards: &[Card]) -> Availability {
+    for card in collection.iter_mut() {
+        card.allocations.remove(deck);
+    }
+    let availability = check::check(cards, collection, None);
+    for &(i, copies) in &availability.used {
+        *collection[i]
+            .allocations
+            .entry(deck.to_string())
+            .or_default() += copies;
+    }
+    availability
+}
+
+/// Moves copies from deck `from` to deck `to`. `None` stands for the free
+/// copies on either side, so this allocates, releases and moves between decks.
+/// Moves the copies matching `lines`, or everything allocated to `from` if
+/// there are none. Fails without moving anything if there aren't enough copies
+/// for a line. Returns how many copies were moved.
+pub fn transfer(
+    collection: &mut [Card],
+    from: Option<&str>,
+    to: Option<&str>,
+    lines: &[ImportLine],
+) -> Result<u32> {
+    let available = |card: &Card| match from {
+        Some(deck) => card.allocated_to(deck),
+        None => card.free(),
+    };
+
+    let mut moved = 0;
+    if lines.is_empty() {
+        let Some(deck) = from else {
+            return Err(anyhow!("Name the cards to allocate."));
+        };
+        for card in collection.iter_mut() {
+            let copies = card.allocated_to(deck);
+            shift(card, from, to, copies);
+            moved += copies;
+        }
+        return Ok(moved);
+    }
+
+    let mut planned = collection.to_vec();
+    for line in lines {
+        let mut remaining = line.count;
+        for card in planned.iter_mut().filter(|c| matches(c, line)) {
+            let copies = available(card).min(remaining);
+            shift(card, from, to, copies);
+            remaining -= copies;
+        }
+        if remaining > 0 {
+            let source = match from {
+                Some(deck) => format!("allocated to {deck}"),
+                None => "free".to_string(),
+            };
+            return Err(anyhow!(
+                "Not enough copies of {} are {source}, {remaining} short.",
+                line.raw
+            ));
+        }
+        moved += line.count;
+    }
+    collection.clone_from_slice(&planned);
+    Ok(moved)
+}
+
+fn shift(card: &mut Card, from: Option<&str>, to: Option<&str>, copies: u32) {
+    if copies == 0 {
+        return;
+    }
+    if let Some(deck) = from {
+        match card.allocated_to(deck) - copies {
+            0 => card.allocations.remove(deck),
+            left => card.allocations.insert(deck.to_string(), left),
+        };
+    }
+    if let Some(deck) = to {
+        *card.allocations.entry(deck.to_string()).or_default() += copies;
+    }
+}
+
+/// Whether `card` is what `line` asks for. Only the name and, if given, the set
+/// and collector number are compared.
+fn matches(card: &Card, line: &ImportLine) -> bool {
+    import::same_name(&card.name, &line.name)
+        && line.set.as_ref().is_none_or(|set| *set == card.set)
+        && line
+            .number
+            .as_ref()
+            .is_none_or(|number| *number == card.collector_number)
+}
+
+/// The copies of `collection` that aren't allocated to any deck.
+pub fn free_copies(collection: &[Card]) -> Vec<Card> {
+    copies(collection, Card::free)
+}
+
+/// The copies of `collection` allocated to `deck`.
+pub fn deck_copies(collection: &[Card], deck: &str) -> Vec<Card> {
+    copies(collection, |c| c.allocated_to(deck))
+}
+
+fn copies(collection: &[Card], count: impl Fn(&Card) -> u32) -> Vec<Card> {
+    collection
+        .iter()
+        .filter(|c| count(c) > 0)
+        .map(|c| Card {
+            count: count(c),
+            allocations: Default::default(),
+            ..c.clone()
+        })
+        .collect()
+}
+
+/// Formats how many copies of `collection` are free, and how many are
+/// allocated to each deck.
+pub fn format_summary(collection: &[Card]) -> String {
+    let total: u32 = collection.iter().map(|c| c.count).sum();
+    let free: u32 = collection.iter().map(Card::free).sum();
+    let mut output = format!("{total} copies, {free} free, {} allocated\n", total - free);
+
+    let mut decks: Vec<(&str, u32)> = Vec::new();
+    for (deck, copies) in collection.iter().flat_map(|c| &c.allocations) {
+        match decks.iter_mut().find(|(d, _)| d == deck) {
+            Some((_, total)) => *total += copies,
+            None => decks.push((deck, *copies)),
+        }
+    }
+    decks.sort();
+    for (deck, copies) in decks {
+        output.push_str(&format!("  {deck:<30}{copies:>5}\n"));
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{Finish, fixture_printing};
+    use pretty_assertions::assert_eq;
+
+    fn line(line: &str) -> ImportLine {
+        import::parse_deck_line(line).unwrap()
+    }
+
+    fn allocations(collection: &[Card]) -> Vec<Vec<(&str, u32)>> {
+        collection
+            .iter()
+            .map(|c| {
+                c.allocations
+                    .iter()
+                    .map(|(d, n)| (d.as_str(), *n))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_transfer() {
+        let mut collection = vec![
+            fixture_printing("Lightning Bolt", "m11", "149", 2),
+            Card {
+                finish: Finish::Foil,
+                ..fixture_printing("Lightning Bolt", "m11", "149", 1)
+            },
+            fixture_printing("Lightning Bolt", "2xm", "129", 4),
+        ];
+
+        let moved = transfer(
+            &mut collection,
+            None,
+            Some("burn"),
+            &[line("3 Lightning Bolt (M11)"), line("1 Lightning Bolt")],
+        )
+        .unwrap();
+        assert_eq!(moved, 4);
+        assert_eq!(
+            allocations(&collection),
+            vec![vec![("burn", 2)], vec![("burn", 1)], vec![("burn", 1)]]
+        );
+
+        transfer(
+            &mut collection,
+            Some("burn"),
+            Some("prowess"),
+            &[line("2 Lightning Bolt")],
+        )
+        .unwrap();
+        assert_eq!(
+            allocations(&collection),
+            vec![vec![("prowess", 2)], vec![("burn", 1)], vec![("burn", 1)]]
+        );
+
+        // Asking for more than there is leaves everything as it was.
+        let before: Vec<_> = collection.iter().map(|c| c.allocations.clone()).collect();
+        assert!(
+            transfer(
+                &mut collection,
+                None,
+                Some("burn"),
+                &[line("4 Lightning Bolt")]
+            )
+            .is_err()
+        );
+        let after: Vec<_> = collection.iter().map(|c| c.allocations.clone()).collect();
+        assert_eq!(before, after);
+
+        assert_eq!(
+            transfer(&mut collection, Some("burn"), None, &[]).unwrap(),
+            2
+        );
+        assert_eq!(
+            allocations(&collection),
+            vec![vec![("prowess", 2)], vec![], vec![]]
+        );
+        assert_eq!(collection[2].free(), 4);
+    }
+
+    #[test]
+    fn test_allocate_deck() {
+        let mut collection = vec![
+            fixture_printing("Lightning Bolt", "m11", "149", 4),
+            fixture_printing("Counterspell", "7ed", "67", 1),
+        ];
+        collection[0].allocations.insert("prowess".to_string(), 3);
+        collection[1].allocations.insert("burn".to_string(), 1);
+
+        let deck = vec![
+            fixture_printing("Lightning Bolt", "m11", "149", 4),
+            fixture_printing("Counterspell", "mh2", "267", 1),
+        ];
+        let availability = allocate_deck(&mut collection, "burn", &deck);
+        assert_eq!(availability.missing.len(), 1);
+        assert_eq!(
+            allocations(&collection),
+            vec![vec![("burn", 1), ("prowess", 3)], vec![("burn", 1)]]
+        );
+        assert_eq!(
+            format_summary(&collection),
+            "5 copies, 0 free, 5 allocated\n  burn                              2\n  prowess                           3\n"
+        );
+        assert_eq!(deck_copies(&collection, "prowess")[0].count, 3);
+        assert!(free_copies(&collection).is_empty());
+    }
+}
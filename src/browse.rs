@@ -0,0 +1,490 @@
+use std::cmp::Ordering;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Row, Table, TableState};
+
+use crate::search::{self, Query, Rarity};
+use crate::types::{Archive, Card, Currency, Finish};
+
+const HELP_TEXT: &str =
+    "q quit  j/k move  s sort  r reverse  / filter  + add  - remove  f foil  d delete";
+
+/// Opens an interactive table over the archive at `path`. Edits made in the
+/// table are written back immediately.
+pub fn run(path: Option<PathBuf>) -> Result<()> {
+    let Archive(cards) = crate::read_collection(path.clone())?;
+    let currency = crate::config::Config::load()?.preferred_currency();
+    let mut browser = Browser::new(path, cards, currency);
+
+    let mut terminal = ratatui::init();
+    let res = browser.run(&mut terminal);
+    ratatui::restore();
+    res
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortColumn {
+    Name,
+    Set,
+    Number,
+    Rarity,
+    Finish,
+    Language,
+    Condition,
+    Count,
+    Price,
+}
+
+impl SortColumn {
+    const ALL: [SortColumn; 9] = [
+        SortColumn::Name,
+        SortColumn::Set,
+        SortColumn::Number,
+        SortColumn::Rarity,
+        SortColumn::Finish,
+        SortColumn::Language,
+        SortColumn::Condition,
+        SortColumn::Count,
+        SortColumn::Price,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            SortColumn::Name => "Name",
+            SortColumn::Set => "Set",
+            SortColumn::Number => "#",
+            SortColumn::Rarity => "Rarity",
+            SortColumn::Finish => "Finish",
+            SortColumn::Language => "Lang",
+            SortColumn::Condition => "Cond",
+            SortColumn::Count => "Count",
+            SortColumn::Price => "Price",
+        }
+    }
+
+    fn next(self) -> SortColumn {
+        let index = SortColumn::ALL.iter().position(|c| *c == self).unwrap();
+        SortColumn::ALL[(index + 1) % SortColumn::ALL.len()]
+    }
+
+    fn compare(self, a: &Card, b: &Card, currency: Currency) -> Ordering {
+        match self {
+            SortColumn::Name => a.name.cmp(&b.name),
+            SortColumn::Set => a.set.cmp(&b.set),
+            SortColumn::Number => {
+                compare_collector_numbers(&a.collector_number, &b.collector_number)
+            }
+            SortColumn::Rarity => Rarity::parse(&a.rarity).cmp(&Rarity::parse(&b.rarity)),
+            SortColumn::Finish => a.finish.cmp(&b.finish),
+            SortColumn::Language => a.language.cmp(&b.language),
+            SortColumn::Condition => a.condition.cmp(&b.condition),
+            SortColumn::Count => a.count.cmp(&b.count),
+            SortColumn::Price => {
+                let price = |c: &Card| c.price(currency).map(|p| p.amount);
+                price(a).cmp(&price(b))
+            }
+        }
+    }
+}
+
+/// Collector numbers are mostly numeric, but not always (`123a`, `★12`), so
+/// they are compared by their leading digits first, and as strings otherwise.
+pub fn compare_collector_numbers(a: &str, b: &str) -> Ordering {
+    let leading_number = |s: &str| -> Option<u32> {
+        let digits: String = s.chars().take_while(char::is_ascii_digit).collect();
+        digits.parse().ok()
+    };
+    leading_number(a)
+        .cmp(&leading_number(b))
+        .then_with(|| a.cmp(b))
+}
+
+/// The modifications that can be made from the browser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Increment,
+    Decrement,
+    ToggleFoil,
+    Delete,
+}
+
+/// Applies `edit` to the entry in `cards` that is the same printing as
+/// `target`. Toggling foil switches between nonfoil and foil, and merges the
+/// entry into an existing one of the other finish, if there is one.
+pub fn apply_edit(cards: &mut Vec<Card>, target: &Card, edit: Edit) -> Result<()> {
+    let Some(index) = cards.iter().position(|c| c.is_same_printing(target)) else {
+        return Err(anyhow!("{} is no longer in this collection.", target.name));
+    };
+
+    match edit {
+        Edit::Increment => cards[index].count += 1,
+        Edit::Decrement => {
+            if cards[index].count == 0 {
+                return Err(anyhow!("No copies of {} left to remove.", target.name));
+            }
+            cards[index].count -= 1;
+            cards[index].clamp_allocations();
+        }
+        Edit::ToggleFoil => {
+            let mut toggled = cards[index].clone();
+            toggled.finish = match toggled.finish {
+                Finish::Nonfoil => Finish::Foil,
+                _ => Finish::Nonfoil,
+            };
+            match cards.iter().position(|c| c.is_same_printing(&toggled)) {
+                Some(other) => {
+                    cards[other].absorb(&toggled);
+                    cards.remove(index);
+                }
+                None => cards[index] = toggled,
+            }
+        }
+        Edit::Delete => {
+            cards.remove(index);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Mode {
+    Normal,
+    Filter,
+}
+
+struct Browser {
+    path: Option<PathBuf>,
+    cards: Vec<Card>,
+    /// The currency the price column sorts by.
+    currency: Currency,
+    /// Indices into `cards`, filtered and sorted.
+    visible: Vec<usize>,
+    table_state: TableState,
+    sort: SortColumn,
+    reverse: bool,
+    filter: Option<Query>,
+    filter_text: String,
+    mode: Mode,
+    status: String,
+    pending_delete: bool,
+}
+
+impl Browser {
+    fn new(path: Option<PathBuf>, cards: Vec<Card>, currency: Currency) -> Browser {
+        let mut browser = Browser {
+            path,
+            cards,
+            currency,
+            visible: Vec::new(),
+            table_state: TableState::default().with_selected(Some(0)),
+            sort: SortColumn::Name,
+            reverse: false,
+            filter: None,
+            filter_text: String::new(),
+            mode: Mode::Normal,
+            status: HELP_TEXT.to_string(),
+            pending_delete: false,
+        };
+        browser.refresh();
+        browser
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                let keep_running = match self.mode {
+                    Mode::Normal => self.handle_normal_key(key),
+                    Mode::Filter => {
+                        self.handle_filter_key(key);
+                        true
+                    }
+                };
+                if !keep_running {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Recomputes the visible rows after the cards, the filter or the sorting
+    /// changed.
+    fn refresh(&mut self) {
+        let mut visible: Vec<usize> = (0..self.cards.len())
+            .filter(|&i| match &self.filter {
+                Some(query) => query.matches(&self.cards[i]),
+                None => true,
+            })
+            .collect();
+        visible.sort_by(|&a, &b| {
+            let ordering = self
+                .sort
+                .compare(&self.cards[a], &self.cards[b], self.currency);
+            match self.reverse {
+                true => ordering.reverse(),
+                false => ordering,
+            }
+        });
+        self.visible = visible;
+
+        let selected = self.table_state.selected().unwrap_or(0);
+        self.table_state
+            .select(Some(selected.min(self.visible.len().saturating_sub(1))));
+    }
+
+    fn selected_card(&self) -> Option<&Card> {
+        self.table_state
+            .selected()
+            .and_then(|i| self.visible.get(i))
+            .map(|&i| &self.cards[i])
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent) -> bool {
+        let delete_requested = key.code == KeyCode::Char('d');
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('j') | KeyCode::Down => self.table_state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.table_state.select_previous(),
+            KeyCode::Char('g') | KeyCode::Home => self.table_state.select_first(),
+            KeyCode::Char('G') | KeyCode::End => self.table_state.select_last(),
+            KeyCode::Char('s') => {
+                self.sort = self.sort.next();
+                self.refresh();
+            }
+            KeyCode::Char('r') => {
+                self.reverse = !self.reverse;
+                self.refresh();
+            }
+            KeyCode::Char('/') => {
+                self.mode = Mode::Filter;
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') => self.edit(Edit::Increment),
+            KeyCode::Char('-') => self.edit(Edit::Decrement),
+            KeyCode::Char('f') => self.edit(Edit::ToggleFoil),
+            KeyCode::Char('d') => match self.pending_delete {
+                true => self.edit(Edit::Delete),
+                false => {
+                    if let Some(card) = self.selected_card() {
+                        self.status = format!("Press d again to delete {}.", card.name);
+                        self.pending_delete = true;
+                        return true;
+                    }
+                }
+            },
+            _ => {}
+        }
+        if !delete_requested {
+            self.pending_delete = false;
+        }
+        true
+    }
+
+    fn handle_filter_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.filter_text.clear();
+                self.filter = None;
+                self.mode = Mode::Normal;
+                self.status = HELP_TEXT.to_string();
+                self.refresh();
+            }
+            KeyCode::Enter => {
+                if self.filter_text.trim().is_empty() {
+                    self.filter = None;
+                } else {
+                    match search::parse_query(&self.filter_text) {
+                        Ok(query) => self.filter = Some(query),
+                        Err(e) => {
+                            self.status = format!("{e}");
+                            return;
+                        }
+                    }
+                }
+                self.mode = Mode::Normal;
+                self.status = HELP_TEXT.to_string();
+                self.refresh();
+            }
+            KeyCode::Backspace => {
+                self.filter_text.pop();
+            }
+            KeyCode::Char(c) => self.filter_text.push(c),
+            _ => {}
+        }
+    }
+
+    /// Applies `edit` to the selected card, through the same read-modify-write
+    /// that `add` uses, and reloads the cards from the result.
+    fn edit(&mut self, edit: Edit) {
+        self.pending_delete = false;
+        let Some(target) = self.selected_card().cloned() else {
+            return;
+        };
+
+        let res = crate::modify_archive(self.path.clone(), |cards| {
+            apply_edit(cards, &target, edit)?;
+            Ok(cards.clone())
+        });
+        match res {
+            Ok(cards) => {
+                self.cards = cards;
+                self.status = match edit {
+                    Edit::Increment => format!("Added a copy of {}.", target.name),
+                    Edit::Decrement => format!("Removed a copy of {}.", target.name),
+                    Edit::ToggleFoil => format!("Toggled foil on {}.", target.name),
+                    Edit::Delete => format!("Deleted {}.", target.name),
+                };
+                self.refresh();
+            }
+            Err(e) => self.status = format!("{e}"),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [table_area, status_area] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+
+        let header = Row::new(SortColumn::ALL.iter().map(|&column| {
+            match (column == self.sort, self.reverse) {
+                (true, false) => format!("{} ▲", column.title()),
+                (true, true) => format!("{} ▼", column.title()),
+                (false, _) => column.title().to_string(),
+            }
+        }))
+        .style(Style::new().bold());
+
+        let rows = self.visible.iter().map(|&i| {
+            let card = &self.cards[i];
+            let price = match (card.eur(), card.usd()) {
+                (Some(eur), Some(usd)) => format!("{eur} / {usd}"),
+                (Some(eur), None) => eur.to_string(),
+                (None, Some(usd)) => usd.to_string(),
+                (None, None) => String::new(),
+            };
+            Row::new(vec![
+                card.name.clone(),
+                card.set.to_ascii_uppercase(),
+                card.collector_number.clone(),
+                card.rarity.clone(),
+                match card.finish {
+                    Finish::Nonfoil => String::new(),
+                    finish => finish.name().to_string(),
+                },
+                card.language.clone(),
+                card.condition.code().to_string(),
+                card.count.to_string(),
+                price,
+            ])
+        });
+
+        let title = match &self.path {
+            Some(path) => path.display().to_string(),
+            None => "Current collection".to_string(),
+        };
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(6),
+                Constraint::Length(6),
+                Constraint::Length(9),
+                Constraint::Length(8),
+                Constraint::Length(4),
+                Constraint::Length(4),
+                Constraint::Length(6),
+                Constraint::Length(18),
+            ],
+        )
+        .header(header)
+        .block(Block::bordered().title(format!(
+            " {title} ({}/{} entries) ",
+            self.visible.len(),
+            self.cards.len()
+        )))
+        .row_highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(table, table_area, &mut self.table_state);
+
+        let status = match self.mode {
+            Mode::Filter => format!("/{}", self.filter_text),
+            Mode::Normal => self.status.clone(),
+        };
+        frame.render_widget(Line::from(status), status_area);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn card(number: &str, foil: bool, count: u32) -> Card {
+        let finish = match foil {
+            true => Finish::Foil,
+            false => Finish::Nonfoil,
+        };
+        Card {
+            name: format!("Card {number}"),
+            collector_number: number.to_string(),
+            set: "blb".to_string(),
+            finish,
+            count,
+            ..Card::default()
+        }
+    }
+
+    #[test]
+    fn test_increment_and_decrement() {
+        let mut cards = vec![card("1", false, 1)];
+        apply_edit(&mut cards, &card("1", false, 1), Edit::Increment).unwrap();
+        assert_eq!(cards[0].count, 2);
+        apply_edit(&mut cards, &card("1", false, 2), Edit::Decrement).unwrap();
+        apply_edit(&mut cards, &card("1", false, 1), Edit::Decrement).unwrap();
+        assert_eq!(cards[0].count, 0);
+        assert!(apply_edit(&mut cards, &card("1", false, 0), Edit::Decrement).is_err());
+
+        cards[0].count = 2;
+        cards[0].allocations.insert("burn".to_string(), 2);
+        apply_edit(&mut cards, &card("1", false, 2), Edit::Decrement).unwrap();
+        assert_eq!(cards[0].allocated_to("burn"), 1);
+    }
+
+    #[test]
+    fn test_toggle_foil_merges() {
+        let mut cards = vec![card("1", false, 2), card("2", false, 1), card("1", true, 1)];
+        cards[0].allocations.insert("burn".to_string(), 2);
+        cards[2].allocations.insert("burn".to_string(), 1);
+        apply_edit(&mut cards, &card("1", false, 2), Edit::ToggleFoil).unwrap();
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[1].finish, Finish::Foil);
+        assert_eq!(cards[1].count, 3);
+        assert_eq!(cards[1].allocated_to("burn"), 3);
+
+        apply_edit(&mut cards, &card("2", false, 1), Edit::ToggleFoil).unwrap();
+        assert_eq!(cards[0].finish, Finish::Foil);
+    }
+
+    #[test]
+    fn test_delete() {
+        let mut cards = vec![card("1", false, 2), card("1", true, 1)];
+        apply_edit(&mut cards, &card("1", true, 1), Edit::Delete).unwrap();
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].finish, Finish::Nonfoil);
+        assert!(apply_edit(&mut cards, &card("1", true, 1), Edit::Delete).is_err());
+    }
+
+    #[test]
+    fn test_collector_number_ordering() {
+        assert_eq!(compare_collector_numbers("2", "10"), Ordering::Less);
+        assert_eq!(compare_collector_numbers("10a", "10"), Ordering::Greater);
+        assert_eq!(compare_collector_numbers("★1", "1"), Ordering::Less);
+    }
+}
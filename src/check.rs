@@ -0,0 +1,298 @@
+use crate::types::{Card, Section};
+
+/// How much of a deck a collection covers. Every deck entry ends up in one or
+/// more of these, split by how many of its copies were found.
+#[derive(Debug, Default)]
+pub struct Availability {
+    /// Deck entries the collection has in the same printing.
+    pub owned: Vec<Card>,
+    /// Deck entries the collection only has in other printings, along with
+    /// those printings and how many copies of them would be used.
+    pub other_printing: Vec<(Card, Vec<Card>)>,
+    /// Deck entries the collection doesn't have at all.
+    pub missing: Vec<Card>,
+    /// The copies used from the collection, as indices into it and how many
+    /// copies of each.
+    pub used: Vec<(usize, u32)>,
+}
+
+/// Compares `deck` against `collection`. Printings are matched by set and
+/// collector number, regardless of finish, language or condition, as those
+/// don't matter for playing a card. Cards are matched by `oracle_id`, or by
+/// name for cards that have none. Each copy in the collection is only counted
+/// once, even if several deck entries could use it, and entries get the copies
+/// in their own printing before others get them as substitutes. Copies
+/// allocated to decks are left out, unless they're allocated to `deck_name`.
+/// Maybeboard entries aren't part of the deck, and are skipped.
+pub fn check(deck: &[Card], collection: &[Card], deck_name: Option<&str>) -> Availability {
+    let mut available: Vec<u32> = collection
+        .iter()
+        .map(|c| c.free() + deck_name.map_or(0, |name| c.allocated_to(name)))
+        .collect();
+    let mut used = Vec::new();
+    let mut availability = Availability::default();
+
+    let mut take = |needed: u32, matches: &dyn Fn(&Card) -> bool| {
+        let mut taken = Vec::new();
+        let mut remaining = needed;
+        for (i, card) in collection.iter().enumerate() {
+            if remaining == 0 {
+                break;
+            }
+            if !matches(card) || available[i] == 0 {
+                continue;
+            }
+            let copies = available[i].min(remaining);
+            available[i] -= copies;
+            remaining -= copies;
+            used.push((i, copies));
+            taken.push(with_count(card, copies));
+        }
+        taken
+    };
+
+    // Exact printings are taken for every entry before any substitutes, so
+    // that an earlier entry can't substitute copies a later one owns exactly.
+    let deck: Vec<&Card> = deck
+        .iter()
+        .filter(|c| c.section != Section::Maybeboard)
+        .collect();
+    let owned: Vec<u32> = deck
+        .iter()
+        .map(|wanted| {
+            let same_printing = take(wanted.count, &|c: &Card| {
+                c.set == wanted.set && c.collector_number == wanted.collector_number
+            });
+            copies(&same_printing)
+        })
+        .collect();
+    let substitutes: Vec<Vec<Card>> = deck
+        .iter()
+        .zip(&owned)
+        .map(|(wanted, owned)| take(wanted.count - owned, &|c: &Card| is_same_card(c, wanted)))
+        .collect();
+
+    for ((wanted, owned), other_printings) in deck.into_iter().zip(owned).zip(substitutes) {
+        if owned > 0 {
+            availability.owned.push(with_count(wanted, owned));
+        }
+
+        let substituted = copies(&other_printings);
+        if substituted > 0 {
+            availability
+                .other_printing
+                .push((with_count(wanted, substituted), other_printings));
+        }
+
+        let missing = wanted.count - owned - substituted;
+        if missing > 0 {
+            availability.missing.push(with_count(wanted, missing));
+        }
+    }
+    availability.used = used;
+    availability
+}
+
+/// Whether `a` and `b` are the same card, in any printing.
+fn is_same_card(a: &Card, b: &Card) -> bool {
+    match (a.oracle_id.is_empty(), b.oracle_id.is_empty()) {
+        (false, false) => a.oracle_id == b.oracle_id,
+        _ => a.name.eq_ignore_ascii_case(&b.name),
+    }
+}
+
+fn with_count(card: &Card, count: u32) -> Card {
+    Card {
+        count,
+        ..card.clone()
+    }
+}
+
+fn copies<'a>(cards: impl IntoIterator<Item = &'a Card>) -> u32 {
+    cards.into_iter().map(|c| c.count).sum()
+}
+
+/// Formats `card` as a deck list line, ie `4 Lightning Bolt (M11) 149 *F*`.
+pub fn format_line(card: &Card) -> String {
+    let line = format!(
+        "{} {} ({}) {} {}",
+        card.count,
+        card.name,
+        card.set.to_ascii_uppercase(),
+        card.collector_number,
+        card.finish.deck_list_marker(),
+    );
+    line.trim_end().to_string()
+}
+
+/// Formats `availability` as one section per outcome, with a header giving
+/// the number of copies in it. Empty sections are left out.
+pub fn format_report(availability: &Availability) -> String {
+    let mut output = String::new();
+
+    if !availability.owned.is_empty() {
+        let owned = copies(&availability.owned);
+        output.push_str(&format!("Owned ({owned}):\n"));
+        for card in &availability.owned {
+            output.push_str(&format!("  {}\n", format_line(card)));
+        }
+    }
+    if !availability.other_printing.is_empty() {
+        let other = copies(availability.other_printing.iter().map(|(c, _)| c));
+        output.push_str(&format!("Owned in another printing ({other}):\n"));
+        for (wanted, have) in &availability.other_printing {
+            let have = have.iter().map(format_line).collect::<Vec<_>>().join(", ");
+            output.push_str(&format!("  {}  (have {have})\n", format_line(wanted)));
+        }
+    }
+    if !availability.missing.is_empty() {
+        let missing = copies(&availability.missing);
+        output.push_str(&format!("Missing ({missing}):\n"));
+        for card in &availability.missing {
+            output.push_str(&format!("  {}\n", format_line(card)));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{Finish, fixture_printing};
+    use pretty_assertions::assert_eq;
+
+    fn summary(cards: &[Card]) -> Vec<(u32, &str, &str)> {
+        cards
+            .iter()
+            .map(|c| (c.count, c.name.as_str(), c.set.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_check() {
+        let deck = vec![
+            fixture_printing("Lightning Bolt", "m11", "149", 4),
+            fixture_printing("Counterspell", "mh2", "267", 2),
+            fixture_printing("Polliwallop", "blb", "189", 3),
+            Card {
+                section: Section::Maybeboard,
+                ..fixture_printing("Bellowing Crier", "blb", "42", 1)
+            },
+        ];
+        let collection = vec![
+            Card {
+                finish: Finish::Foil,
+                ..fixture_printing("Lightning Bolt", "m11", "149", 1)
+            },
+            fixture_printing("Lightning Bolt", "m11", "149", 2),
+            fixture_printing("Lightning Bolt", "2xm", "129", 5),
+            fixture_printing("Counterspell", "7ed", "67", 1),
+        ];
+
+        let availability = check(&deck, &collection, None);
+        assert_eq!(
+            summary(&availability.owned),
+            vec![(3, "Lightning Bolt", "m11")]
+        );
+        let other: Vec<_> = availability
+            .other_printing
+            .iter()
+            .map(|(wanted, have)| (wanted.count, wanted.name.as_str(), summary(have)))
+            .collect();
+        assert_eq!(
+            other,
+            vec![
+                (1, "Lightning Bolt", vec![(1, "Lightning Bolt", "2xm")]),
+                (1, "Counterspell", vec![(1, "Counterspell", "7ed")]),
+            ]
+        );
+        assert_eq!(
+            summary(&availability.missing),
+            vec![(1, "Counterspell", "mh2"), (3, "Polliwallop", "blb")]
+        );
+
+        let report = format_report(&availability);
+        assert!(report.contains("Owned (3):\n  3 Lightning Bolt (M11) 149\n"));
+        assert!(report.contains(
+            "Owned in another printing (2):\n  1 Lightning Bolt (M11) 149  (have 1 Lightning Bolt (2XM) 129)\n"
+        ));
+        assert!(
+            report
+                .contains("Missing (4):\n  1 Counterspell (MH2) 267\n  3 Polliwallop (BLB) 189\n")
+        );
+    }
+
+    #[test]
+    fn test_copies_are_only_used_once() {
+        let deck = vec![
+            fixture_printing("Lightning Bolt", "m11", "149", 2),
+            fixture_printing("Lightning Bolt", "2xm", "129", 2),
+        ];
+        let collection = vec![fixture_printing("Lightning Bolt", "m11", "149", 3)];
+
+        let availability = check(&deck, &collection, None);
+        assert_eq!(
+            summary(&availability.owned),
+            vec![(2, "Lightning Bolt", "m11")]
+        );
+        assert_eq!(availability.other_printing.len(), 1);
+        assert_eq!(
+            summary(&availability.missing),
+            vec![(1, "Lightning Bolt", "2xm")]
+        );
+    }
+
+    #[test]
+    fn test_exact_printings_come_before_substitutes() {
+        let deck = vec![
+            fixture_printing("Lightning Bolt", "2xm", "129", 2),
+            fixture_printing("Lightning Bolt", "m11", "149", 2),
+        ];
+        let collection = vec![fixture_printing("Lightning Bolt", "m11", "149", 2)];
+
+        let availability = check(&deck, &collection, None);
+        assert_eq!(
+            summary(&availability.owned),
+            vec![(2, "Lightning Bolt", "m11")]
+        );
+        assert!(availability.other_printing.is_empty());
+        assert_eq!(
+            summary(&availability.missing),
+            vec![(2, "Lightning Bolt", "2xm")]
+        );
+    }
+
+    #[test]
+    fn test_allocated_copies_are_only_used_by_their_deck() {
+        let deck = vec![fixture_printing("Lightning Bolt", "m11", "149", 4)];
+        let mut bolts = fixture_printing("Lightning Bolt", "m11", "149", 4);
+        bolts.allocations.insert("burn".to_string(), 1);
+        bolts.allocations.insert("prowess".to_string(), 2);
+        let collection = vec![bolts];
+
+        let availability = check(&deck, &collection, None);
+        assert_eq!(
+            summary(&availability.missing),
+            vec![(3, "Lightning Bolt", "m11")]
+        );
+        let availability = check(&deck, &collection, Some("burn"));
+        assert_eq!(
+            summary(&availability.owned),
+            vec![(2, "Lightning Bolt", "m11")]
+        );
+        assert_eq!(availability.used, vec![(0, 2)]);
+    }
+
+    #[test]
+    fn test_cards_without_oracle_id_match_by_name() {
+        let deck = vec![Card {
+            oracle_id: String::new(),
+            ..fixture_printing("Lightning Bolt", "lea", "161", 1)
+        }];
+        let collection = vec![fixture_printing("lightning bolt", "m11", "149", 1)];
+
+        let availability = check(&deck, &collection, None);
+        assert_eq!(availability.other_printing.len(), 1);
+        assert!(availability.missing.is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::BTreeSet;
+
+use rustyline::Context;
+use rustyline::Helper;
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+
+/// Tab completion for the `add` REPL. Completes card names after a `"` or `=`,
+/// from the names of cards that are already in the collection.
+#[derive(Debug, Default)]
+pub struct CardNameCompleter {
+    names: BTreeSet<String>,
+}
+
+impl CardNameCompleter {
+    pub fn new(names: impl IntoIterator<Item = String>) -> CardNameCompleter {
+        CardNameCompleter {
+            names: names.into_iter().collect(),
+        }
+    }
+
+    pub fn insert(&mut self, name: String) {
+        self.names.insert(name);
+    }
+
+    /// Returns the byte offset the replacement starts at, and the candidates.
+    fn candidates(&self, line: &str) -> (usize, Vec<Pair>) {
+        let Some(start) = line.find(['"', '=']) else {
+            return (0, Vec::new());
+        };
+        let typed = line[start..]
+            .trim_start_matches('=')
+            .trim_start_matches('"');
+        // A closed quote means the name is complete.
+        if line[start..].matches('"').count() > 1
+            || (!line[start..].contains('"') && typed.contains(' '))
+        {
+            return (0, Vec::new());
+        }
+
+        let quote = match &line[start..start + 1] {
+            "=" => "=\"",
+            _ => "\"",
+        };
+        let typed = typed.to_lowercase();
+        let candidates = self
+            .names
+            .iter()
+            .filter(|name| name.to_lowercase().starts_with(&typed))
+            .map(|name| Pair {
+                display: name.clone(),
+                replacement: format!("{quote}{name}\""),
+            })
+            .collect();
+        (start, candidates)
+    }
+}
+
+impl Completer for CardNameCompleter {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        Ok(self.candidates(&line[..pos]))
+    }
+}
+
+impl Hinter for CardNameCompleter {
+    type Hint = String;
+}
+
+impl Highlighter for CardNameCompleter {}
+
+impl Validator for CardNameCompleter {}
+
+impl Helper for CardNameCompleter {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn replacements(completer: &CardNameCompleter, line: &str) -> (usize, Vec<String>) {
+        let (start, pairs) = completer.candidates(line);
+        (start, pairs.into_iter().map(|p| p.replacement).collect())
+    }
+
+    #[test]
+    fn test_completes_names() {
+        let completer = CardNameCompleter::new(vec![
+            "Lightning Bolt".to_string(),
+            "Lightning Helix".to_string(),
+            "Polliwallop".to_string(),
+        ]);
+
+        assert_eq!(
+            replacements(&completer, "2x\"light"),
+            (
+                2,
+                vec![
+                    "\"Lightning Bolt\"".to_string(),
+                    "\"Lightning Helix\"".to_string()
+                ]
+            )
+        );
+        assert_eq!(
+            replacements(&completer, "=poll"),
+            (0, vec!["=\"Polliwallop\"".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_no_completion_outside_names() {
+        let completer = CardNameCompleter::new(vec!["Polliwallop".to_string()]);
+        assert!(completer.candidates("189").1.is_empty());
+        assert!(completer.candidates("\"Polliwallop\" bl").1.is_empty());
+        assert!(completer.candidates("=Polliwallop bl").1.is_empty());
+    }
+}
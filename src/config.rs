@@ -0,0 +1,308 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use anyhow::anyhow;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::export::ExportType;
+use crate::scryfall::SCRYFALL_API_ROOT;
+use crate::types::Currency;
+
+/// Settings from the config file, see `config_file_path`. Everything is
+/// optional, unset settings fall back to their defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Config {
+    /// Where lists, the collection and the card database live.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_dir: Option<PathBuf>,
+    /// The format `export` uses without `--format`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_export_format: Option<ExportType>,
+    /// The currency cards are ranked and sorted by.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preferred_currency: Option<Currency>,
+    /// Where to find Scryfall's API, ie a mirror or a caching proxy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scryfall_base_url: Option<String>,
+    /// The set code `add` defaults to without `--set-code`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_set: Option<String>,
+}
+
+/// The names `config get/set` know the settings by, in the order they're
+/// listed.
+pub const KEYS: [&str; 5] = [
+    "data_dir",
+    "default_export_format",
+    "preferred_currency",
+    "scryfall_base_url",
+    "default_set",
+];
+
+impl Config {
+    /// Reads the config file. A missing file is the same as an empty one.
+    pub fn load() -> Result<Config> {
+        let path = config_file_path()?;
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| anyhow!("Config file {} is invalid: {e}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(anyhow!(
+                "Could not read config file {}: {e}",
+                path.display()
+            )),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = config_file_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| anyhow!("Can't create folder at {}: {e}", dir.display()))?;
+        }
+        crate::write_atomically(&path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// The value of setting `key`, `None` if it's unset.
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        let value = match key {
+            "data_dir" => self.data_dir.as_ref().map(|d| d.display().to_string()),
+            "default_export_format" => self.default_export_format.map(value_name),
+            "preferred_currency" => self.preferred_currency.map(|c| c.code().to_string()),
+            "scryfall_base_url" => self.scryfall_base_url.clone(),
+            "default_set" => self.default_set.clone(),
+            _ => return Err(unknown_key(key)),
+        };
+        Ok(value)
+    }
+
+    /// Changes setting `key` to `value`, or back to its default for `None`.
+    pub fn set(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        match key {
+            // Relative paths would resolve against wherever later commands
+            // run from, so they're pinned to the current folder now.
+            "data_dir" => {
+                self.data_dir = value
+                    .map(|v| {
+                        std::path::absolute(v).map_err(|e| anyhow!("Invalid path {v}: {e}"))
+                    })
+                    .transpose()?
+            }
+            "default_export_format" => {
+                self.default_export_format = value
+                    .map(|v| ExportType::from_str(v, true))
+                    .transpose()
+                    .map_err(|_| {
+                        let formats = ExportType::value_variants().iter().map(|f| value_name(*f));
+                        anyhow!(
+                            "Unknown export format, expected one of: {}",
+                            formats.collect::<Vec<_>>().join(", ")
+                        )
+                    })?
+            }
+            "preferred_currency" => {
+                self.preferred_currency = value
+                    .map(|v| {
+                        Currency::parse(v)
+                            .ok_or_else(|| anyhow!("Unknown currency {v}, expected usd or eur."))
+                    })
+                    .transpose()?
+            }
+            "scryfall_base_url" => {
+                if let Some(url) = value {
+                    reqwest::Url::parse(url).map_err(|e| anyhow!("Invalid URL {url}: {e}"))?;
+                }
+                self.scryfall_base_url = value.map(str::to_string)
+            }
+            "default_set" => self.default_set = value.map(str::to_ascii_lowercase),
+            _ => return Err(unknown_key(key)),
+        }
+        Ok(())
+    }
+
+    /// Where lists, the collection and the card database live. The first of
+    /// these that is set wins: `data_dir`, `$CRACKATHON_HOME`,
+    /// `$XDG_DATA_HOME/crack`, and `~/.config/crack`, see `default_data_dir`.
+    pub fn data_dir(&self) -> Result<PathBuf> {
+        if let Some(data_dir) = &self.data_dir {
+            if !data_dir.is_absolute() {
+                return Err(anyhow!(
+                    "data_dir must be an absolute path, it is {}.",
+                    data_dir.display()
+                ));
+            }
+            return Ok(data_dir.clone());
+        }
+        if let Some(home) = env_path("CRACKATHON_HOME") {
+            return Ok(home);
+        }
+        Ok(default_data_dir(env_path("XDG_DATA_HOME"), &home_dir()?))
+    }
+
+    pub fn preferred_currency(&self) -> Currency {
+        self.preferred_currency.unwrap_or_default()
+    }
+
+    pub fn scryfall_base_url(&self) -> &str {
+        self.scryfall_base_url
+            .as_deref()
+            .unwrap_or(SCRYFALL_API_ROOT)
+    }
+}
+
+/// `$XDG_DATA_HOME/crack` if `data_home` is set, `~/.config/crack` otherwise.
+/// Data used to always live in the latter, so it's kept there as long as it
+/// has a collection and the former doesn't, rather than starting over with an
+/// empty collection.
+fn default_data_dir(data_home: Option<PathBuf>, home: &Path) -> PathBuf {
+    let legacy = home.join(".config").join("crack");
+    let Some(data_home) = data_home else {
+        return legacy;
+    };
+    let xdg = data_home.join("crack");
+    match (
+        legacy.join("collection.json").exists(),
+        xdg.join("collection.json").exists(),
+    ) {
+        (true, false) => legacy,
+        _ => xdg,
+    }
+}
+
+/// Where the config file lives: `$CRACKATHON_HOME/config.json` if that's set,
+/// `$XDG_CONFIG_HOME/crack/config.json` or `~/.config/crack/config.json`
+/// otherwise.
+pub fn config_file_path() -> Result<PathBuf> {
+    let dir = match (env_path("CRACKATHON_HOME"), env_path("XDG_CONFIG_HOME")) {
+        (Some(home), _) => home,
+        (None, Some(config_home)) => config_home.join("crack"),
+        (None, None) => home_dir()?.join(".config").join("crack"),
+    };
+    Ok(dir.join("config.json"))
+}
+
+/// The environment variable `name` as a path, if it's set to an absolute
+/// one. The XDG spec says relative paths are to be ignored.
+fn env_path(name: &str) -> Option<PathBuf> {
+    env::var_os(name)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+}
+
+fn home_dir() -> Result<PathBuf> {
+    env::home_dir().ok_or_else(|| anyhow!("Can't get user home directory."))
+}
+
+fn value_name(format: ExportType) -> String {
+    format
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+fn unknown_key(key: &str) -> anyhow::Error {
+    anyhow!(
+        "Unknown setting {key}, expected one of: {}",
+        KEYS.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get_set() {
+        let mut config = Config::default();
+        for key in KEYS {
+            assert_eq!(config.get(key).unwrap(), None);
+        }
+
+        config.set("default_export_format", Some("CSV")).unwrap();
+        config.set("preferred_currency", Some("eur")).unwrap();
+        config.set("default_set", Some("BLB")).unwrap();
+        config
+            .set("scryfall_base_url", Some("http://localhost:8080/"))
+            .unwrap();
+        assert_eq!(config.get("default_export_format").unwrap().unwrap(), "csv");
+        assert_eq!(config.get("preferred_currency").unwrap().unwrap(), "eur");
+        assert_eq!(config.get("default_set").unwrap().unwrap(), "blb");
+        assert_eq!(config.scryfall_base_url(), "http://localhost:8080/");
+
+        config.set("default_set", None).unwrap();
+        assert_eq!(config.get("default_set").unwrap(), None);
+
+        assert!(config.set("preferred_currency", Some("gold")).is_err());
+        assert!(config.set("default_export_format", Some("pdf")).is_err());
+        assert!(config.set("scryfall_base_url", Some("not a url")).is_err());
+        assert!(config.set("colour", Some("blue")).is_err());
+        assert!(config.get("colour").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let config: Config =
+            serde_json::from_str(r#"{"default_export_format": "deck", "default_set": "blb"}"#)
+                .unwrap();
+        assert_eq!(config.default_export_format, Some(ExportType::Deck));
+        assert_eq!(
+            serde_json::to_string(&config).unwrap(),
+            r#"{"default_export_format":"deck","default_set":"blb"}"#
+        );
+    }
+
+    #[test]
+    fn test_data_dir_setting_wins() {
+        let config = Config {
+            data_dir: Some(PathBuf::from("/srv/cards")),
+            ..Config::default()
+        };
+        assert_eq!(config.data_dir().unwrap(), PathBuf::from("/srv/cards"));
+
+        let relative = Config {
+            data_dir: Some(PathBuf::from("cards")),
+            ..Config::default()
+        };
+        assert!(relative.data_dir().is_err());
+    }
+
+    #[test]
+    fn test_set_data_dir_makes_it_absolute() {
+        let mut config = Config::default();
+        config.set("data_dir", Some("cards")).unwrap();
+        assert_eq!(
+            config.data_dir.unwrap(),
+            env::current_dir().unwrap().join("cards")
+        );
+    }
+
+    #[test]
+    fn test_legacy_data_dir_is_kept() {
+        let home = env::temp_dir().join(format!("crackathon-test-{}-legacy", std::process::id()));
+        let _ = fs::remove_dir_all(&home);
+        let legacy = home.join(".config").join("crack");
+        let data_home = home.join(".local").join("share");
+        assert_eq!(default_data_dir(None, &home), legacy);
+        assert_eq!(
+            default_data_dir(Some(data_home.clone()), &home),
+            data_home.join("crack")
+        );
+
+        fs::create_dir_all(&legacy).unwrap();
+        fs::write(legacy.join("collection.json"), "[]").unwrap();
+        assert_eq!(default_data_dir(Some(data_home.clone()), &home), legacy);
+
+        // Once there's a collection in the new place, that one is used.
+        fs::create_dir_all(data_home.join("crack")).unwrap();
+        fs::write(data_home.join("crack").join("collection.json"), "[]").unwrap();
+        assert_eq!(
+            default_data_dir(Some(data_home.clone()), &home),
+            data_home.join("crack")
+        );
+        fs::remove_dir_all(home).unwrap();
+    }
+}
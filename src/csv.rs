@@ -0,0 +1,100 @@
+use anyhow::Result;
+use anyhow::anyhow;
+
+/// Splits RFC 4180 CSV into records of fields. Fields may be quoted, in which
+/// case they can contain commas, newlines and `""` as an escaped quote. Both
+/// `\n` and `\r\n` line endings are accepted, and blank lines are skipped.
+pub fn parse(input: &str) -> Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) => match chars.peek() {
+                Some('"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                _ => in_quotes = false,
+            },
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                if !(record.len() == 1 && record[0].is_empty()) {
+                    records.push(std::mem::take(&mut record));
+                }
+                record.clear();
+            }
+            (c, _) => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(anyhow!("CSV ends inside a quoted field."));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Formats `fields` as one RFC 4180 CSV record. Every field is quoted, like
+/// Moxfield and most stores do, with quotes inside it doubled, and the record
+/// ends in CRLF.
+pub fn format_record<S: AsRef<str>>(fields: &[S]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|f| format!("\"{}\"", f.as_ref().replace('"', "\"\"")))
+        .collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_simple() {
+        let records = parse("a,b,c\n1,2,3\n").unwrap();
+        assert_eq!(records, vec![vec!["a", "b", "c"], vec!["1", "2", "3"]]);
+    }
+
+    #[test]
+    fn test_parse_quoted() {
+        let records = parse(
+            "\"Count\",\"Name\"\r\n\"1\",\"Borborygmos, \"\"Enraged\"\"\"\r\n\n2,\"Two\nLines\"",
+        )
+        .unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec!["Count", "Name"],
+                vec!["1", "Borborygmos, \"Enraged\""],
+                vec!["2", "Two\nLines"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_empty_fields() {
+        assert_eq!(parse("a,,\n").unwrap(), vec![vec!["a", "", ""]]);
+        assert!(parse("\"unterminated").is_err());
+    }
+
+    #[test]
+    fn test_format_record_round_trips() {
+        let fields = ["1", "Borborygmos, \"Enraged\"", "Two\nLines", ""];
+        let record = format_record(&fields);
+        assert_eq!(
+            record,
+            "\"1\",\"Borborygmos, \"\"Enraged\"\"\",\"Two\nLines\",\"\"\r\n"
+        );
+        assert_eq!(parse(&record).unwrap(), vec![fields.to_vec()]);
+    }
+}
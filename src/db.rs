@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{BufReader, Read};
+use std::path::PathBuf;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use serde::Deserializer;
+use serde::de::{SeqAccess, Visitor};
+
+use crate::types::{Card, Finish};
+
+/// A local copy of Scryfall's card data, built from one of their bulk data
+/// files (https://scryfall.com/docs/api/bulk-data). The bulk file is one huge
+/// JSON array, so it's split up into one file per set on import, each a map
+/// from collector number to card. A lookup then only has to read a single
+/// small file.
+pub struct CardDb {
+    root: PathBuf,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportStats {
+    pub cards: usize,
+    pub sets: usize,
+    /// Entries in the bulk file that didn't parse as a `Card`.
+    pub skipped: usize,
+}
+
+type SetIndex = BTreeMap<String, Card>;
+
+impl CardDb {
+    pub fn open(root: PathBuf) -> CardDb {
+        CardDb { root }
+    }
+
+    /// Replaces the contents of the database with the cards in `bulk_file`,
+    /// which is expected to be a JSON array of Scryfall card objects.
+    pub fn import(&self, bulk_file: impl Read) -> Result<ImportStats> {
+        let mut sets: BTreeMap<String, SetIndex> = BTreeMap::new();
+        let mut stats = ImportStats::default();
+
+        let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(bulk_file));
+        deserializer.deserialize_seq(BulkVisitor {
+            sets: &mut sets,
+            stats: &mut stats,
+        })?;
+        deserializer.end()?;
+
+        if self.root.exists() {
+            fs::remove_dir_all(&self.root)?;
+        }
+        fs::create_dir_all(&self.root)?;
+        for (set, cards) in &sets {
+            let file_content = serde_json::to_string(cards)?;
+            fs::write(self.set_path(set), file_content)?;
+        }
+
+        stats.sets = sets.len();
+        Ok(stats)
+    }
+
+    /// Looks up a single printing. Returns `None` if the set or the card is
+    /// not in the database.
+    pub fn get(&self, set: &str, number: &str) -> Result<Option<Card>> {
+        Ok(self
+            .read_set(set)?
+            .and_then(|mut index| index.remove(number)))
+    }
+
+    /// How many printings of `set` the database has. Returns `None` if the
+    /// set is not in the database.
+    pub fn set_size(&self, set: &str) -> Result<Option<usize>> {
+        Ok(self.read_set(set)?.map(|index| index.len()))
+    }
+
+    /// All printings of `set` in the database, by collector number. Returns
+    /// `None` if the set is not in the database.
+    pub fn set_cards(&self, set: &str) -> Result<Option<Vec<Card>>> {
+        Ok(self
+            .read_set(set)?
+            .map(|index| index.into_values().collect()))
+    }
+
+    /// Finds all printings of cards named `name`, in `set` if given, in all
+    /// sets otherwise. Exact (case-insensitive) matches are preferred, if there
+    /// are none, every card whose name contains `name` is returned.
+    pub fn find_by_name(&self, name: &str, set: Option<&str>) -> Result<Vec<Card>> {
+        let sets = match set {
+            Some(set) => vec![set.to_ascii_lowercase()],
+            None => self.sets()?,
+        };
+
+        let name = name.to_lowercase();
+        let mut exact = Vec::new();
+        let mut partial = Vec::new();
+        for set in sets {
+            for card in self
+                .read_set(&set)?
+                .into_iter()
+                .flat_map(|i| i.into_values())
+            {
+                let card_name = card.name.to_lowercase();
+                if card_name == name {
+                    exact.push(card);
+                } else if card_name.contains(&name) {
+                    partial.push(card);
+                }
+            }
+        }
+
+        Ok(match exact.is_empty() {
+            true => partial,
+            false => exact,
+        })
+    }
+
+    /// All set codes in the database.
+    fn sets(&self) -> Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) => match e.kind() {
+                std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                _ => return Err(anyhow!("Could not read card database: {e}")),
+            },
+        };
+
+        let mut sets = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if let Some(set) = path.file_stem().and_then(|s| s.to_str()) {
+                sets.push(set.to_string());
+            }
+        }
+        sets.sort();
+        Ok(sets)
+    }
+
+    fn read_set(&self, set: &str) -> Result<Option<SetIndex>> {
+        let file = match fs::read_to_string(self.set_path(set)) {
+            Ok(res) => res,
+            Err(e) => match e.kind() {
+                std::io::ErrorKind::NotFound => return Ok(None),
+                _ => return Err(anyhow!("Could not read card database: {e}")),
+            },
+        };
+        match serde_json::from_str(&file) {
+            Ok(index) => Ok(Some(index)),
+            Err(e) => Err(anyhow!("Card database is not valid JSON: {e}")),
+        }
+    }
+
+    fn set_path(&self, set: &str) -> PathBuf {
+        self.root.join(format!("{}.json", set.to_ascii_lowercase()))
+    }
+}
+
+/// Walks the bulk data array one card at a time, so that the entire file never
+/// has to be held in memory as one `serde_json::Value`.
+struct BulkVisitor<'a> {
+    sets: &'a mut BTreeMap<String, SetIndex>,
+    stats: &'a mut ImportStats,
+}
+
+impl<'de> Visitor<'de> for BulkVisitor<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of Scryfall card objects")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(value) = seq.next_element::<serde_json::Value>()? {
+            let mut card: Card = match serde_json::from_value(value) {
+                Ok(card) => card,
+                Err(_) => {
+                    self.stats.skipped += 1;
+                    continue;
+                }
+            };
+            card.count = 1;
+            card.finish = Finish::Nonfoil;
+            card.set = card.set.to_ascii_lowercase();
+
+            self.stats.cards += 1;
+            self.sets
+                .entry(card.set.clone())
+                .or_default()
+                .insert(card.collector_number.clone(), card);
+        }
+        Ok(())
+    }
+}
+
+/// Builds a database from the fixture bulk file in a fresh temporary
+/// directory, so that lookups can be tested without network access.
+#[cfg(test)]
+pub fn fixture_db(name: &str) -> CardDb {
+    let root = std::env::temp_dir().join(format!("crackathon-test-{}-{name}", std::process::id()));
+    let db = CardDb::open(root);
+    let fixture = fs::File::open(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/fixtures/default_cards.json"
+    ))
+    .unwrap();
+    db.import(fixture).unwrap();
+    db
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_import_fixture() {
+        let root =
+            std::env::temp_dir().join(format!("crackathon-test-{}-import", std::process::id()));
+        let db = CardDb::open(root.clone());
+        let fixture = fs::File::open(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fixtures/default_cards.json"
+        ))
+        .unwrap();
+
+        let stats = db.import(fixture).unwrap();
+        assert_eq!(
+            stats,
+            ImportStats {
+                cards: 3,
+                sets: 2,
+                skipped: 0
+            }
+        );
+        assert!(root.join("blb.json").exists());
+        assert!(root.join("m11.json").exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_lookup() {
+        let db = fixture_db("lookup");
+
+        let card = db.get("BLB", "189").unwrap().unwrap();
+        assert_eq!(card.name, "Polliwallop");
+        assert_eq!(card.count, 1);
+        assert_eq!(card.finish, Finish::Nonfoil);
+        assert_eq!(card.language, "en");
+
+        assert!(db.get("blb", "9999").unwrap().is_none());
+        assert!(db.get("xyz", "1").unwrap().is_none());
+
+        assert_eq!(db.set_size("blb").unwrap(), Some(2));
+        assert_eq!(db.set_size("xyz").unwrap(), None);
+        let numbers: Vec<_> = db
+            .set_cards("blb")
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.collector_number)
+            .collect();
+        assert_eq!(numbers, vec!["189", "42"]);
+    }
+
+    #[test]
+    fn test_find_by_name() {
+        let db = fixture_db("find-by-name");
+
+        let cards = db.find_by_name("lightning bolt", None).unwrap();
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].set, "m11");
+
+        let cards = db.find_by_name("l", Some("blb")).unwrap();
+        let names: Vec<_> = cards.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Polliwallop", "Bellowing Crier"]);
+
+        assert!(db.find_by_name("bolt", Some("blb")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reject_non_array() {
+        let db = fixture_db("reject");
+        assert!(db.import("{}".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,644 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::csv;
+use crate::types::{self, Card, Condition, Finish, Section};
+
+/// The formats `export` can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportType {
+    /// A deck list, as read by Arena, Moxfield et al.
+    Deck,
+    /// A Moxfield collection CSV.
+    #[value(alias = "moxfield")]
+    #[serde(alias = "moxfield")]
+    Csv,
+    /// An MTGO deck file (`.dek`).
+    Mtgo,
+    /// A Cockatrice deck file (`.cod`).
+    Cockatrice,
+    /// A Deckbox inventory CSV.
+    Deckbox,
+    /// A TCGplayer collection CSV.
+    Tcgplayer,
+    /// A Cardmarket stock CSV.
+    Cardmarket,
+    /// A plain JSON array of cards.
+    Json,
+}
+
+impl ExportType {
+    pub fn exporter(self) -> Box<dyn Exporter> {
+        match self {
+            ExportType::Deck => Box::new(DeckList),
+            ExportType::Csv => Box::new(Moxfield::default()),
+            ExportType::Mtgo => Box::new(Mtgo),
+            ExportType::Cockatrice => Box::new(Cockatrice),
+            ExportType::Deckbox => Box::new(Deckbox),
+            ExportType::Tcgplayer => Box::new(Tcgplayer),
+            ExportType::Cardmarket => Box::new(Cardmarket),
+            ExportType::Json => Box::new(Json),
+        }
+    }
+}
+
+/// Turns a list of cards into a file some other tool can import.
+pub trait Exporter {
+    fn export(&self, cards: &[Card]) -> String;
+}
+
+/// The deck list format accepted by Arena, Moxfield et al, one printing per
+/// line: `4 Lightning Bolt (M11) 149 *F*`. Lists with cards outside the
+/// mainboard get a header above each section, ie `Sideboard`.
+pub struct DeckList;
+
+impl Exporter for DeckList {
+    fn export(&self, cards: &[Card]) -> String {
+        let line = |card: &Card| {
+            format!(
+                "{} {} ({}) {} {}\n",
+                card.count,
+                card.name,
+                card.set.to_ascii_uppercase(),
+                card.collector_number,
+                card.finish.deck_list_marker()
+            )
+        };
+        if cards.iter().all(|c| c.section.is_mainboard()) {
+            return cards.iter().map(line).collect();
+        }
+
+        let mut output = String::new();
+        for section in Section::ALL {
+            let lines: String = cards
+                .iter()
+                .filter(|c| c.section == section)
+                .map(line)
+                .collect();
+            if lines.is_empty() {
+                continue;
+            }
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            output.push_str(&format!("{}\n{lines}", section.header()));
+        }
+        output
+    }
+}
+
+/// Moxfield's collection CSV, see
+/// https://moxfield.com/help/importing-collection. All columns Moxfield
+/// imports are written by default, so that files round-trip through `import`.
+pub struct Moxfield {
+    pub columns: Vec<Column>,
+}
+
+impl Default for Moxfield {
+    fn default() -> Moxfield {
+        Moxfield {
+            columns: Column::value_variants().to_vec(),
+        }
+    }
+}
+
+/// A column of Moxfield's collection CSV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Column {
+    Count,
+    Name,
+    Edition,
+    Condition,
+    Language,
+    Foil,
+    Tags,
+    CollectorNumber,
+    Alter,
+    Proxy,
+    PurchasePrice,
+}
+
+impl Column {
+    pub fn header(self) -> &'static str {
+        match self {
+            Column::Count => "Count",
+            Column::Name => "Name",
+            Column::Edition => "Edition",
+            Column::Condition => "Condition",
+            Column::Language => "Language",
+            Column::Foil => "Foil",
+            Column::Tags => "Tags",
+            Column::CollectorNumber => "Collector Number",
+            Column::Alter => "Alter",
+            Column::Proxy => "Proxy",
+            Column::PurchasePrice => "Purchase Price",
+        }
+    }
+
+    fn value(self, card: &Card) -> String {
+        let flag = |set: bool| match set {
+            true => "True".to_string(),
+            false => "False".to_string(),
+        };
+        match self {
+            Column::Count => card.count.to_string(),
+            Column::Name => card.name.clone(),
+            Column::Edition => card.set.clone(),
+            Column::Condition => match card.condition {
+                Condition::NearMint => "Near Mint",
+                Condition::LightlyPlayed => "Lightly Played",
+                Condition::ModeratelyPlayed => "Moderately Played",
+                Condition::HeavilyPlayed => "Heavily Played",
+                Condition::Damaged => "Damaged",
+            }
+            .to_string(),
+            Column::Language => types::language_name(&card.language).to_string(),
+            Column::Foil => match card.finish {
+                Finish::Nonfoil => "",
+                Finish::Etched => "etched",
+                _ => "foil",
+            }
+            .to_string(),
+            Column::Tags => card.tags.join(", "),
+            Column::CollectorNumber => card.collector_number.clone(),
+            Column::Alter => flag(card.alter),
+            Column::Proxy => flag(card.proxy),
+            Column::PurchasePrice => card
+                .purchase_price
+                .map(|p| p.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Exporter for Moxfield {
+    fn export(&self, cards: &[Card]) -> String {
+        let headers: Vec<&str> = self.columns.iter().map(|c| c.header()).collect();
+        let mut output = csv::format_record(&headers);
+        for card in cards {
+            let fields: Vec<String> = self.columns.iter().map(|c| c.value(card)).collect();
+            output.push_str(&csv::format_record(&fields));
+        }
+        output
+    }
+}
+
+/// MTGO's XML deck file. MTGO goes by its own card ids, which Scryfall
+/// provides for most printings. Without one, MTGO falls back to the name, for
+/// which split cards are written as `Fire/Ice`. MTGO keeps the commander in
+/// the sideboard, and has no maybeboard.
+pub struct Mtgo;
+
+impl Exporter for Mtgo {
+    fn export(&self, cards: &[Card]) -> String {
+        let mut output = String::from(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <Deck xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n  \
+             <NetDeckID>0</NetDeckID>\n  \
+             <PreconstructedDeckID>0</PreconstructedDeckID>\n",
+        );
+        let cards: Vec<Card> = cards
+            .iter()
+            .filter(|c| c.section != Section::Maybeboard)
+            .cloned()
+            .collect();
+        let entries = group(&cards, |card| {
+            let id = match card.finish {
+                Finish::Nonfoil => card.mtgo_id,
+                _ => card.mtgo_foil_id,
+            };
+            let sideboard = card.section != Section::Mainboard;
+            (id, card.name.replace(" // ", "/"), sideboard)
+        });
+        for ((id, name, sideboard), count) in entries {
+            let id = id.map(|id| format!("CatID=\"{id}\" ")).unwrap_or_default();
+            output.push_str(&format!(
+                "  <Cards {id}Quantity=\"{count}\" Sideboard=\"{sideboard}\" Name=\"{}\" Annotation=\"0\" />\n",
+                escape_xml(&name)
+            ));
+        }
+        output.push_str("</Deck>\n");
+        output
+    }
+}
+
+/// Cockatrice's XML deck file. Cockatrice only knows cards by name, so
+/// printings of the same card are merged. It has a main and a side zone, the
+/// commander goes in the former, the maybeboard is left out.
+pub struct Cockatrice;
+
+impl Exporter for Cockatrice {
+    fn export(&self, cards: &[Card]) -> String {
+        let mut output = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <cockatrice_deck version=\"1\">\n    \
+             <deckname></deckname>\n    \
+             <comments></comments>\n",
+        );
+        let zones = [
+            ("main", vec![Section::Commander, Section::Mainboard]),
+            ("side", vec![Section::Sideboard]),
+        ];
+        for (zone, sections) in zones {
+            let cards: Vec<Card> = cards
+                .iter()
+                .filter(|c| sections.contains(&c.section))
+                .cloned()
+                .collect();
+            if cards.is_empty() && zone == "side" {
+                continue;
+            }
+            output.push_str(&format!("    <zone name=\"{zone}\">\n"));
+            for (name, count) in group(&cards, |card| card.name.clone()) {
+                output.push_str(&format!(
+                    "        <card number=\"{count}\" name=\"{}\"/>\n",
+                    escape_xml(&name)
+                ));
+            }
+            output.push_str("    </zone>\n");
+        }
+        output.push_str("</cockatrice_deck>\n");
+        output
+    }
+}
+
+/// Deckbox's inventory CSV, see https://deckbox.org/help/csv. Deckbox knows
+/// sets by their full name.
+pub struct Deckbox;
+
+impl Exporter for Deckbox {
+    fn export(&self, cards: &[Card]) -> String {
+        let mut output = csv::format_record(&[
+            "Count",
+            "Name",
+            "Edition",
+            "Card Number",
+            "Condition",
+            "Language",
+            "Foil",
+        ]);
+        for card in cards {
+            let condition = match card.condition {
+                Condition::NearMint => "Near Mint",
+                Condition::LightlyPlayed => "Good (Lightly Played)",
+                Condition::ModeratelyPlayed => "Played",
+                Condition::HeavilyPlayed => "Heavily Played",
+                Condition::Damaged => "Poor",
+            };
+            output.push_str(&csv::format_record(&[
+                &card.count.to_string(),
+                &card.name,
+                &card.set_name,
+                &card.collector_number,
+                condition,
+                types::language_name(&card.language),
+                foil(card),
+            ]));
+        }
+        output
+    }
+}
+
+/// TCGplayer's collection CSV. TCGplayer matches on the product id, if
+/// Scryfall has one.
+pub struct Tcgplayer;
+
+impl Exporter for Tcgplayer {
+    fn export(&self, cards: &[Card]) -> String {
+        let mut output = csv::format_record(&[
+            "Quantity",
+            "Name",
+            "Set",
+            "Card Number",
+            "Set Code",
+            "Printing",
+            "Condition",
+            "Language",
+            "Product ID",
+        ]);
+        for card in cards {
+            let printing = match card.finish {
+                Finish::Nonfoil => "Normal",
+                _ => "Foil",
+            };
+            let condition = match card.condition {
+                Condition::NearMint => "Near Mint",
+                Condition::LightlyPlayed => "Lightly Played",
+                Condition::ModeratelyPlayed => "Moderately Played",
+                Condition::HeavilyPlayed => "Heavily Played",
+                Condition::Damaged => "Damaged",
+            };
+            output.push_str(&csv::format_record(&[
+                &card.count.to_string(),
+                &card.name,
+                &card.set_name,
+                &card.collector_number,
+                &card.set.to_ascii_uppercase(),
+                printing,
+                condition,
+                types::language_name(&card.language),
+                &card
+                    .tcgplayer_id
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+            ]));
+        }
+        output
+    }
+}
+
+/// A CSV for Cardmarket's stock import. Cardmarket matches on the product id,
+/// if Scryfall has one, and grades cards on its own scale, so conditions are
+/// converted the way most stores do.
+pub struct Cardmarket;
+
+impl Exporter for Cardmarket {
+    fn export(&self, cards: &[Card]) -> String {
+        let mut output = csv::format_record(&[
+            "Amount",
+            "Name",
+            "Expansion",
+            "Collector Number",
+            "idProduct",
+            "Language",
+            "Condition",
+            "Foil",
+        ]);
+        for card in cards {
+            let condition = match card.condition {
+                Condition::NearMint => "NM",
+                Condition::LightlyPlayed => "EX",
+                Condition::ModeratelyPlayed => "GD",
+                Condition::HeavilyPlayed => "PL",
+                Condition::Damaged => "PO",
+            };
+            output.push_str(&csv::format_record(&[
+                &card.count.to_string(),
+                &card.name,
+                &card.set_name,
+                &card.collector_number,
+                &card
+                    .cardmarket_id
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+                types::language_name(&card.language),
+                condition,
+                foil(card),
+            ]));
+        }
+        output
+    }
+}
+
+/// A JSON array with the fields that identify a physical card, for scripts.
+/// Unlike an archive, this leaves out prices and other Scryfall data.
+pub struct Json;
+
+#[derive(Serialize)]
+struct JsonCard<'a> {
+    count: u32,
+    name: &'a str,
+    set: &'a str,
+    collector_number: &'a str,
+    finish: Finish,
+    language: &'a str,
+    condition: Condition,
+    section: Section,
+    oracle_id: &'a str,
+}
+
+impl Exporter for Json {
+    fn export(&self, cards: &[Card]) -> String {
+        let cards: Vec<JsonCard> = cards
+            .iter()
+            .map(|card| JsonCard {
+                count: card.count,
+                name: &card.name,
+                set: &card.set,
+                collector_number: &card.collector_number,
+                finish: card.finish,
+                language: &card.language,
+                condition: card.condition,
+                section: card.section,
+                oracle_id: &card.oracle_id,
+            })
+            .collect();
+        let mut output = serde_json::to_string_pretty(&cards).unwrap_or_default();
+        output.push('\n');
+        output
+    }
+}
+
+fn foil(card: &Card) -> &'static str {
+    match card.finish.is_foil() {
+        true => "foil",
+        false => "",
+    }
+}
+
+/// Sums up the counts of `cards` by `key`, in the order keys first appear.
+fn group<K: PartialEq>(cards: &[Card], key: impl Fn(&Card) -> K) -> Vec<(K, u32)> {
+    let mut groups: Vec<(K, u32)> = Vec::new();
+    for card in cards {
+        let key = key(card);
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, count)) => *count += card.count,
+            None => groups.push((key, card.count)),
+        }
+    }
+    groups
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    fn cards() -> Vec<Card> {
+        let card = |name: &str, set: &str, set_name: &str, number: &str, count: u32| Card {
+            name: name.to_string(),
+            oracle_id: format!("oracle-{number}"),
+            set: set.to_string(),
+            set_name: set_name.to_string(),
+            collector_number: number.to_string(),
+            count,
+            language: "en".to_string(),
+            ..Card::default()
+        };
+        vec![
+            Card {
+                mtgo_id: Some(37787),
+                tcgplayer_id: Some(36491),
+                cardmarket_id: Some(22180),
+                ..card("Lightning Bolt", "m11", "Magic 2011", "149", 4)
+            },
+            Card {
+                finish: Finish::Foil,
+                condition: Condition::LightlyPlayed,
+                mtgo_foil_id: Some(37788),
+                tags: vec!["burn".to_string(), "to trade".to_string()],
+                purchase_price: Some(rust_decimal::Decimal::new(1250, 2)),
+                ..card("Lightning Bolt", "m11", "Magic 2011", "149", 1)
+            },
+            Card {
+                finish: Finish::Etched,
+                language: "ja".to_string(),
+                condition: Condition::Damaged,
+                alter: true,
+                ..card("Fire // Ice", "mh2", "Modern Horizons 2", "290", 2)
+            },
+            Card {
+                condition: Condition::ModeratelyPlayed,
+                proxy: true,
+                ..card(
+                    "Borborygmos, \"Enraged\" & <Co>",
+                    "rna",
+                    "Ravnica Allegiance",
+                    "157",
+                    1,
+                )
+            },
+        ]
+    }
+
+    /// The same cards, spread over the sections of a deck.
+    fn deck() -> Vec<Card> {
+        let sections = [
+            Section::Mainboard,
+            Section::Sideboard,
+            Section::Commander,
+            Section::Maybeboard,
+        ];
+        cards()
+            .into_iter()
+            .zip(sections)
+            .map(|(card, section)| Card { section, ..card })
+            .collect()
+    }
+
+    /// Compares `format`'s export with its golden file in `fixtures/export`.
+    /// Set `UPDATE_GOLDEN` to write the current output instead.
+    fn assert_golden(format: ExportType, cards: &[Card], file: &str) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/export")
+            .join(file);
+        let output = format.exporter().export(cards);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, &output).unwrap();
+        }
+        assert_eq!(output, std::fs::read_to_string(&path).unwrap());
+    }
+
+    #[test]
+    fn test_deck_list() {
+        assert_golden(ExportType::Deck, &cards(), "deck.txt");
+        assert_golden(ExportType::Deck, &deck(), "deck-sections.txt");
+    }
+
+    #[test]
+    fn test_deck_list_sections_round_trip() {
+        let (lines, unresolved) = crate::import::parse_deck_list(&DeckList.export(&deck()));
+        assert!(unresolved.is_empty());
+        let sections: Vec<_> = lines.iter().map(|l| (l.name.as_str(), l.section)).collect();
+        let expected: Vec<_> = deck().into_iter().map(|c| (c.name, c.section)).collect();
+        let mut expected: Vec<_> = expected.iter().map(|(n, s)| (n.as_str(), *s)).collect();
+        expected.sort_by_key(|(_, section)| Section::ALL.iter().position(|s| s == section));
+        assert_eq!(sections, expected);
+    }
+
+    #[test]
+    fn test_moxfield() {
+        assert_golden(ExportType::Csv, &cards(), "moxfield.csv");
+    }
+
+    #[test]
+    fn test_moxfield_round_trips() {
+        let cards = cards();
+        let (lines, unresolved) =
+            crate::import::parse_moxfield_csv(&Moxfield::default().export(&cards)).unwrap();
+        assert!(unresolved.is_empty());
+        for (line, card) in lines.iter().zip(&cards) {
+            assert_eq!(
+                (
+                    line.count,
+                    &line.name,
+                    line.set.as_deref(),
+                    line.number.as_deref(),
+                    line.finish,
+                    &line.language,
+                    line.condition,
+                    &line.tags,
+                    line.purchase_price,
+                    line.alter,
+                    line.proxy,
+                ),
+                (
+                    card.count,
+                    &card.name,
+                    Some(card.set.as_str()),
+                    Some(card.collector_number.as_str()),
+                    card.finish,
+                    &card.language,
+                    card.condition,
+                    &card.tags,
+                    card.purchase_price,
+                    card.alter,
+                    card.proxy,
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_moxfield_columns() {
+        let moxfield = Moxfield {
+            columns: vec![Column::Name, Column::Count],
+        };
+        assert_eq!(
+            moxfield.export(&cards()[..1]),
+            "\"Name\",\"Count\"\r\n\"Lightning Bolt\",\"4\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_mtgo() {
+        assert_golden(ExportType::Mtgo, &cards(), "mtgo.dek");
+        assert_golden(ExportType::Mtgo, &deck(), "mtgo-sections.dek");
+    }
+
+    #[test]
+    fn test_cockatrice() {
+        assert_golden(ExportType::Cockatrice, &cards(), "cockatrice.cod");
+        assert_golden(ExportType::Cockatrice, &deck(), "cockatrice-sections.cod");
+    }
+
+    #[test]
+    fn test_deckbox() {
+        assert_golden(ExportType::Deckbox, &cards(), "deckbox.csv");
+    }
+
+    #[test]
+    fn test_tcgplayer() {
+        assert_golden(ExportType::Tcgplayer, &cards(), "tcgplayer.csv");
+    }
+
+    #[test]
+    fn test_cardmarket() {
+        assert_golden(ExportType::Cardmarket, &cards(), "cardmarket.csv");
+    }
+
+    #[test]
+    fn test_json() {
+        assert_golden(ExportType::Json, &cards(), "cards.json");
+    }
+}
@@ -0,0 +1,490 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use rust_decimal::Decimal;
+
+use crate::db::CardDb;
+use crate::scryfall::{self, Client, NameMatch};
+use crate::types::{self, Card, Condition, Finish, Section};
+
+/// One card line of an imported file, before it's been looked up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportLine {
+    /// The line as a deck list line, for the report of unresolved lines.
+    pub raw: String,
+    pub count: u32,
+    pub name: String,
+    pub set: Option<String>,
+    pub number: Option<String>,
+    pub finish: Finish,
+    pub language: String,
+    pub condition: Condition,
+    pub section: Section,
+    /// Only found in CSVs, see `Card::tags`.
+    pub tags: Vec<String>,
+    pub purchase_price: Option<Decimal>,
+    pub alter: bool,
+    pub proxy: bool,
+}
+
+/// A line that couldn't be imported, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unresolved {
+    pub raw: String,
+    pub reason: String,
+}
+
+/// Parses a deck list as exported by Arena, Moxfield et al, one card per line:
+/// `4 Lightning Bolt (M11) 149 *F*`. Everything but the name is optional.
+/// Section headers like `Sideboard` put the cards below them in that section.
+/// Arena lists the companion both under `Companion` and in the sideboard, so
+/// the former is skipped, as is its `About` section. Blank lines and `//`
+/// comments are skipped too. Lines that don't parse are returned as
+/// unresolved.
+pub fn parse_deck_list(input: &str) -> (Vec<ImportLine>, Vec<Unresolved>) {
+    let mut lines = Vec::new();
+    let mut unresolved = Vec::new();
+    let mut section = Some(Section::Mainboard);
+    for line in input.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") || line.starts_with('#') {
+            continue;
+        }
+        let header = line.trim_end_matches(':');
+        if let Some(parsed) = Section::parse(header) {
+            section = Some(parsed);
+            continue;
+        }
+        if ["companion", "about"].contains(&header.to_ascii_lowercase().as_str()) {
+            section = None;
+            continue;
+        }
+        let Some(section) = section else {
+            continue;
+        };
+        match parse_deck_line(line) {
+            Ok(parsed) => lines.push(ImportLine { section, ..parsed }),
+            Err(e) => unresolved.push(Unresolved {
+                raw: line.to_string(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+    (lines, unresolved)
+}
+
+pub fn parse_deck_line(line: &str) -> Result<ImportLine> {
+    let (count, rest) = match line.split_once(char::is_whitespace) {
+        Some((count, rest)) => match count.trim_end_matches('x').parse::<u32>() {
+            Ok(count) => (count, rest.trim()),
+            Err(_) => (1, line),
+        },
+        None => (1, line),
+    };
+    if count == 0 {
+        return Err(anyhow!("Count must be at least 1."));
+    }
+
+    let (rest, finish) = match rest.rsplit_once(' ') {
+        Some((rest, "*F*")) => (rest.trim(), Finish::Foil),
+        Some((rest, "*E*")) => (rest.trim(), Finish::Etched),
+        _ => (rest, Finish::Nonfoil),
+    };
+
+    let (name, set, number) = match rest.rfind(" (") {
+        Some(open) => {
+            let Some(close) = rest[open..].find(')').map(|i| open + i) else {
+                return Err(anyhow!("Set code is missing its closing parenthesis."));
+            };
+            let set = rest[open + 2..close].trim().to_ascii_lowercase();
+            let number = rest[close + 1..].trim();
+            (
+                rest[..open].trim(),
+                Some(set),
+                (!number.is_empty()).then(|| number.to_string()),
+            )
+        }
+        None => (rest.trim(), None, None),
+    };
+    if name.is_empty() {
+        return Err(anyhow!("Line has no card name."));
+    }
+
+    Ok(ImportLine {
+        raw: line.to_string(),
+        count,
+        name: name.to_string(),
+        set,
+        number,
+        finish,
+        language: "en".to_string(),
+        condition: Condition::default(),
+        section: Section::default(),
+        tags: Vec::new(),
+        purchase_price: None,
+        alter: false,
+        proxy: false,
+    })
+}
+
+/// Parses a Moxfield collection CSV export, see
+/// https://moxfield.com/help/importing-collection. Columns are found by their
+/// header, only `Count` and `Name` are required.
+pub fn parse_moxfield_csv(input: &str) -> Result<(Vec<ImportLine>, Vec<Unresolved>)> {
+    let mut records = crate::csv::parse(input)?.into_iter();
+    let Some(header) = records.next() else {
+        return Ok((Vec::new(), Vec::new()));
+    };
+    let columns: HashMap<String, usize> = header
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.trim().to_ascii_lowercase(), i))
+        .collect();
+    for required in ["count", "name"] {
+        if !columns.contains_key(required) {
+            return Err(anyhow!(
+                "CSV has no `{required}` column, is this a Moxfield export?"
+            ));
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut unresolved = Vec::new();
+    for record in records {
+        let field = |column: &str| {
+            columns
+                .get(column)
+                .and_then(|&i| record.get(i))
+                .map(|f| f.trim())
+                .filter(|f| !f.is_empty())
+        };
+        let mut line = ImportLine {
+            raw: String::new(),
+            count: 1,
+            name: field("name").unwrap_or_default().to_string(),
+            set: field("edition").map(str::to_ascii_lowercase),
+            number: field("collector number").map(str::to_string),
+            finish: match field("foil") {
+                Some("etched") => Finish::Etched,
+                Some(_) => Finish::Foil,
+                None => Finish::Nonfoil,
+            },
+            language: "en".to_string(),
+            condition: Condition::default(),
+            section: Section::default(),
+            tags: field("tags")
+                .map(|tags| tags.split(',').map(|t| t.trim().to_string()).collect())
+                .unwrap_or_default(),
+            purchase_price: None,
+            alter: false,
+            proxy: false,
+        };
+        let checked = (|| {
+            line.count = match field("count").map(str::parse::<u32>) {
+                Some(Ok(count)) if count > 0 => count,
+                _ => return Err(anyhow!("Count must be a number of at least 1.")),
+            };
+            if line.name.is_empty() {
+                return Err(anyhow!("Line has no card name."));
+            }
+            if let Some(condition) = field("condition") {
+                line.condition = Condition::parse(condition)
+                    .ok_or_else(|| anyhow!("Unknown condition `{condition}`."))?;
+            }
+            if let Some(language) = field("language") {
+                line.language = types::language_code(language)
+                    .ok_or_else(|| anyhow!("Unknown language `{language}`."))?
+                    .to_string();
+            }
+            if let Some(price) = field("purchase price") {
+                line.purchase_price = Some(
+                    price
+                        .parse()
+                        .map_err(|_| anyhow!("Purchase price `{price}` is not a number."))?,
+                );
+            }
+            for (column, flag) in [("alter", &mut line.alter), ("proxy", &mut line.proxy)] {
+                *flag = match field(column).map(str::to_ascii_lowercase).as_deref() {
+                    None | Some("false") => false,
+                    Some("true") => true,
+                    Some(other) => return Err(anyhow!("`{other}` is neither True nor False.")),
+                };
+            }
+            Ok(())
+        })();
+        line.raw = format_deck_line(&line);
+        match checked {
+            Ok(()) => lines.push(line),
+            Err(e) => unresolved.push(Unresolved {
+                raw: line.raw,
+                reason: e.to_string(),
+            }),
+        }
+    }
+    Ok((lines, unresolved))
+}
+
+fn format_deck_line(line: &ImportLine) -> String {
+    let mut raw = format!("{} {}", line.count, line.name);
+    if let Some(set) = &line.set {
+        raw.push_str(&format!(" ({})", set.to_ascii_uppercase()));
+    }
+    if let Some(number) = &line.number {
+        raw.push_str(&format!(" {number}"));
+    }
+    if line.finish != Finish::Nonfoil {
+        raw.push_str(&format!(" {}", line.finish.deck_list_marker()));
+    }
+    raw
+}
+
+/// Looks every line up, via the local card database if possible, Scryfall
+/// otherwise. A set code and collector number are tried first. If those are
+/// missing, or point at a different card, the name is looked up instead, and
+/// the first printing is taken if it matches several.
+pub fn resolve(
+    lines: Vec<ImportLine>,
+    client: &Client,
+    db: &CardDb,
+) -> (Vec<Card>, Vec<Unresolved>) {
+    let mut cards = Vec::new();
+    let mut unresolved = Vec::new();
+    for line in lines {
+        match resolve_line(&line, client, db) {
+            Ok(mut card) => {
+                card.count = line.count;
+                card.finish = line.finish;
+                card.language = line.language;
+                card.condition = line.condition;
+                card.section = line.section;
+                card.tags = line.tags;
+                card.purchase_price = line.purchase_price;
+                card.alter = line.alter;
+                card.proxy = line.proxy;
+                cards.push(card);
+            }
+            Err(e) => unresolved.push(Unresolved {
+                raw: line.raw,
+                reason: e.to_string(),
+            }),
+        }
+    }
+    (cards, unresolved)
+}
+
+fn resolve_line(line: &ImportLine, client: &Client, db: &CardDb) -> Result<Card> {
+    if let (Some(set), Some(number)) = (&line.set, &line.number)
+        && let Ok(card) = scryfall::query_card(set, number, client, db)
+        && same_name(&card.name, &line.name)
+    {
+        return Ok(card);
+    }
+
+    match scryfall::query_card_by_name(&line.name, line.set.as_deref(), client, db)? {
+        NameMatch::Card(card) => Ok(*card),
+        NameMatch::Printings(mut cards) => Ok(cards.remove(0)),
+        NameMatch::Ambiguous(names) => Err(anyhow!(
+            "Ambiguous name, could be any of: {}",
+            names.join(", ")
+        )),
+    }
+}
+
+/// Whether `a` and `b` name the same card. Deck lists often only name the
+/// front face of double-faced cards, so only that is compared.
+pub fn same_name(a: &str, b: &str) -> bool {
+    let front = |name: &str| {
+        name.split(" // ")
+            .next()
+            .unwrap_or(name)
+            .trim()
+            .to_lowercase()
+    };
+    front(a) == front(b)
+}
+
+/// Adds `cards` to `archive`, merging the counts of cards already in it.
+pub fn merge(archive: &mut Vec<Card>, cards: Vec<Card>) {
+    for card in cards {
+        match archive.iter_mut().find(|c| c.is_same_printing(&card)) {
+            Some(existing) => existing.count += card.count,
+            None => archive.push(card),
+        }
+    }
+}
+
+/// Adds `cards` to the collection `archive`, like `merge`. The collection
+/// isn't a deck, so sideboard, commander and maybeboard cards all join the
+/// copies already in it.
+pub fn merge_into_collection(archive: &mut Vec<Card>, cards: Vec<Card>) {
+    let cards = cards
+        .into_iter()
+        .map(|card| Card {
+            section: Section::Mainboard,
+            ..card
+        })
+        .collect();
+    merge(archive, cards);
+}
+
+/// Formats unresolved lines as a deck list, with the reason as a comment above
+/// each line. Once fixed up, the report can be imported again.
+pub fn format_report(unresolved: &[Unresolved]) -> String {
+    let mut output = String::new();
+    for line in unresolved {
+        output.push_str(&format!("// {}\n{}\n", line.reason, line.raw));
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::fixture_db;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_deck_list() {
+        let (lines, unresolved) = parse_deck_list(
+            "Deck\n4 Lightning Bolt (M11) 149\n\n// comment\n1x Polliwallop (BLB) 189 *F*\nSideboard:\nBellowing Crier\n2 Fire // Ice (MH2) 290 *E*\n0 Nothing\n",
+        );
+
+        let summary: Vec<_> = lines
+            .iter()
+            .map(|l| {
+                (
+                    l.count,
+                    l.name.as_str(),
+                    l.set.as_deref(),
+                    l.number.as_deref(),
+                    l.finish,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    4,
+                    "Lightning Bolt",
+                    Some("m11"),
+                    Some("149"),
+                    Finish::Nonfoil
+                ),
+                (1, "Polliwallop", Some("blb"), Some("189"), Finish::Foil),
+                (1, "Bellowing Crier", None, None, Finish::Nonfoil),
+                (2, "Fire // Ice", Some("mh2"), Some("290"), Finish::Etched),
+            ]
+        );
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!(unresolved[0].raw, "0 Nothing");
+        let sections: Vec<_> = lines.iter().map(|l| l.section).collect();
+        assert_eq!(
+            sections,
+            vec![
+                Section::Mainboard,
+                Section::Mainboard,
+                Section::Sideboard,
+                Section::Sideboard
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_deck_list_sections() {
+        let (lines, _) = parse_deck_list(
+            "Commander\n1 Polliwallop\n\nCompanion\n1 Lurrus of the Dream-Den\n\nDeck\n4 Lightning Bolt\n\nSideboard\n1 Lurrus of the Dream-Den\n\nMaybeboard\n1 Bellowing Crier\n",
+        );
+        let summary: Vec<_> = lines
+            .iter()
+            .map(|l| (l.name.as_str(), l.section))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Polliwallop", Section::Commander),
+                ("Lightning Bolt", Section::Mainboard),
+                ("Lurrus of the Dream-Den", Section::Sideboard),
+                ("Bellowing Crier", Section::Maybeboard),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_moxfield_csv() {
+        let (lines, unresolved) = parse_moxfield_csv(
+            "\"Count\",\"Tradelist Count\",\"Name\",\"Edition\",\"Condition\",\"Language\",\"Foil\",\"Collector Number\"\n\
+             \"2\",\"0\",\"Lightning Bolt\",\"m11\",\"Lightly Played\",\"Japanese\",\"foil\",\"149\"\n\
+             \"1\",\"0\",\"Polliwallop\",\"blb\",\"Near Mint\",\"English\",\"\",\"189\"\n\
+             \"1\",\"0\",\"Bellowing Crier\",\"blb\",\"Near Mint\",\"Klingon\",\"\",\"42\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].count, 2);
+        assert_eq!(lines[0].finish, Finish::Foil);
+        assert_eq!(lines[0].condition, Condition::LightlyPlayed);
+        assert_eq!(lines[0].language, "ja");
+        assert_eq!(lines[1].raw, "1 Polliwallop (BLB) 189");
+        assert_eq!(
+            unresolved,
+            vec![Unresolved {
+                raw: "1 Bellowing Crier (BLB) 42".to_string(),
+                reason: "Unknown language `Klingon`.".to_string(),
+            }]
+        );
+        assert!(parse_moxfield_csv("Name,Edition\n").is_err());
+    }
+
+    #[test]
+    fn test_resolve_and_merge() {
+        let db = fixture_db("import-resolve");
+        let client = Client::new(scryfall::SCRYFALL_API_ROOT).unwrap();
+        let (lines, _) = parse_deck_list(
+            "4 Lightning Bolt (M11) 149\n1 Polliwallop (BLB) 42 *F*\n2 bellowing crier\n1 L (BLB)\n",
+        );
+
+        let (cards, unresolved) = resolve(lines, &client, &db);
+        let summary: Vec<_> = cards
+            .iter()
+            .map(|c| (c.count, c.name.as_str(), c.collector_number.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (4, "Lightning Bolt", "149"),
+                // The number points at a different card, the name wins.
+                (1, "Polliwallop", "189"),
+                (2, "Bellowing Crier", "42"),
+            ]
+        );
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!(
+            format_report(&unresolved),
+            "// Ambiguous name, could be any of: Bellowing Crier, Polliwallop\n1 L (BLB)\n"
+        );
+
+        let mut archive = vec![cards[0].clone()];
+        merge(&mut archive, cards);
+        assert_eq!(archive.len(), 3);
+        assert_eq!(archive[0].count, 8);
+    }
+
+    #[test]
+    fn test_merge_sideboard_into_collection() {
+        let db = fixture_db("import-collection");
+        let client = Client::new(scryfall::SCRYFALL_API_ROOT).unwrap();
+        let (lines, _) = parse_deck_list(
+            "Deck\n3 Lightning Bolt (M11) 149\n\nSideboard\n1 Lightning Bolt (M11) 149\n",
+        );
+        let (cards, _) = resolve(lines, &client, &db);
+        assert_eq!(cards[1].section, Section::Sideboard);
+
+        let mut collection = vec![cards[0].clone()];
+        merge_into_collection(&mut collection, cards);
+        assert_eq!(collection.len(), 1);
+        assert_eq!(collection[0].count, 7);
+        assert_eq!(collection[0].section, Section::Mainboard);
+    }
+}
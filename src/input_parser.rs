@@ -1,45 +1,168 @@
 use anyhow::Result;
 use anyhow::anyhow;
 
+use crate::types::{Condition, Finish, LANGUAGES};
+
 #[derive(Default, Debug, Eq, PartialEq)]
 pub struct Input {
     pub card_number: String,
+    /// Set when the card was given by name rather than by collector number, in
+    /// which case `card_number` is empty.
+    pub card_name: Option<String>,
+    /// The set to look the card up in. Can only be empty for cards given by
+    /// name, which then match any printing.
     pub set_code: String,
-    pub foil: bool,
+    pub finish: Finish,
+    /// Scryfall language code, `en` unless given.
+    pub language: String,
+    pub condition: Condition,
     pub removal: bool,
+    /// How many copies to add or remove. Always at least 1.
+    pub count: u32,
 }
 
 /// Parse the input given on the REPL. This is slightly tricky as this is
 /// essentially a highly compact DSL. Previously this was a lot more freeform,
 /// but now this is accepting exactly two words of input: the form
-/// `<-><collector number><f> <setcode>`. This means that `12 dsk` is valid
-/// input, but `dsk 12` is not.
+/// `<-><count x><collector number><f> <setcode>`, optionally followed by a
+/// `*<count>`. This means that `12 dsk` is valid input, but `dsk 12` is not.
 ///
 /// - `-12 dsk` removes one of those copies from the  collection.
 /// - `12f dsk` adds a foil version.
+/// - `4x12 dsk` or `12 dsk *4` add four copies, `-3x12 dsk` removes three.
+/// - `"lightning bolt" m11` or `=bolt` look the card up by name instead. Names
+///   don't need a set code, and `"lightning bolt"f` adds a foil version.
+/// - `12e`, `12g` and `12t` add etched, gilded and textured foils. Any finish
+///   can also be given as a word, ie `12 dsk +surge`.
+/// - `12 dsk ja` adds a Japanese copy, `12 dsk @lp` a lightly played one.
 pub fn parse_addition_input(input: String, provided_set_code: Option<String>) -> Result<Input> {
-    let mut res = Input::default();
-
-    let word_clone = input.clone();
-    let mut word_split = word_clone.split_ascii_whitespace();
-
-    let mut number = word_split
-        .next()
-        .expect("a non-empty input line has at least one word?")
-        .to_string();
-    let set_code = word_split.next();
+    let mut res = Input {
+        count: 1,
+        language: "en".to_string(),
+        ..Input::default()
+    };
 
-    if number.starts_with('-') {
+    let mut rest = input.trim();
+    if let Some(stripped) = rest.strip_prefix('-') {
         res.removal = true;
-        number = number.strip_prefix('-').unwrap().to_string();
+        rest = stripped;
     }
 
-    if number.ends_with('f') {
-        res.foil = true;
-        number = number.strip_suffix('f').unwrap().to_string();
+    let prefixed_quantity = match rest.split_once('x') {
+        Some((quantity, stripped))
+            if !quantity.is_empty() && quantity.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            rest = stripped;
+            Some(parse_quantity(quantity)?)
+        }
+        _ => None,
+    };
+
+    let remainder = match rest.strip_prefix('=') {
+        Some(name) if !name.starts_with('"') => {
+            let (name, remainder) = name.split_once(char::is_whitespace).unwrap_or((name, ""));
+            if name.is_empty() {
+                return Err(anyhow!("No card name given after `=`."));
+            }
+            res.card_name = Some(name.to_string());
+            remainder
+        }
+        _ => match rest.trim_start_matches('=').strip_prefix('"') {
+            Some(quoted) => {
+                let Some((name, remainder)) = quoted.split_once('"') else {
+                    return Err(anyhow!("Card name is missing its closing quote."));
+                };
+                if name.trim().is_empty() {
+                    return Err(anyhow!("No card name given between the quotes."));
+                }
+                res.card_name = Some(name.trim().to_string());
+                let (suffix, remainder) = remainder
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((remainder, ""));
+                res.finish = match suffix {
+                    "" => Finish::Nonfoil,
+                    s => match finish_suffix(s) {
+                        Some(finish) => finish,
+                        None => return Err(anyhow!("Unknown finish after card name: {s}")),
+                    },
+                };
+                remainder
+            }
+            None => {
+                let (number, remainder) =
+                    rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                if number.is_empty() {
+                    return Err(anyhow!("No collector number given."));
+                }
+                let mut number = number.to_string();
+                if let Some(finish) = number.get(number.len() - 1..).and_then(finish_suffix) {
+                    res.finish = finish;
+                    number.pop();
+                }
+                if number.is_empty() || number.starts_with('*') {
+                    return Err(anyhow!("No collector number given."));
+                }
+                res.card_number = number;
+                remainder
+            }
+        },
+    };
+
+    let mut set_code = None;
+    let mut suffixed_quantity = None;
+    let mut language = None;
+    let mut condition = None;
+    let mut finish = None;
+    for word in remainder.split_ascii_whitespace() {
+        let already_given = if let Some(quantity) = word.strip_prefix('*') {
+            suffixed_quantity
+                .replace(parse_quantity(quantity)?)
+                .is_some()
+        } else if let Some(c) = word.strip_prefix('@') {
+            let c = Condition::parse(c).ok_or_else(|| {
+                anyhow!("Unknown condition: {c}, expected one of NM, LP, MP, HP or DMG.")
+            })?;
+            condition.replace(c).is_some()
+        } else if let Some(f) = word.strip_prefix('+') {
+            let f = Finish::parse(f).ok_or_else(|| anyhow!("Unknown finish: {f}"))?;
+            finish.replace(f).is_some()
+        } else if LANGUAGES
+            .iter()
+            .any(|(code, _)| code.eq_ignore_ascii_case(word))
+        {
+            language.replace(word.to_ascii_lowercase()).is_some()
+        } else {
+            set_code.replace(word).is_some()
+        };
+        if already_given {
+            return Err(anyhow!("Too many words in input, unexpected `{word}`."));
+        }
+    }
+    if let Some(language) = language {
+        res.language = language;
+    }
+    if let Some(condition) = condition {
+        res.condition = condition;
     }
+    if let Some(finish) = finish {
+        if res.finish != Finish::Nonfoil {
+            return Err(anyhow!(
+                "Finish was given twice, use either a suffix or `+finish`."
+            ));
+        }
+        res.finish = finish;
+    }
+
+    res.count = match (prefixed_quantity, suffixed_quantity) {
+        (Some(_), Some(_)) => {
+            return Err(anyhow!(
+                "Quantity was given twice, use either `Nx<number>` or `*N`."
+            ));
+        }
+        (Some(quantity), None) | (None, Some(quantity)) => quantity,
+        (None, None) => 1,
+    };
 
-    res.card_number = number.parse()?;
     res.set_code = match set_code {
         Some(set) => match set.chars().all(char::is_alphanumeric) {
             true => set.to_string(),
@@ -58,6 +181,7 @@ pub fn parse_addition_input(input: String, provided_set_code: Option<String>) ->
                     ));
                 }
             },
+            None if res.card_name.is_some() => String::new(),
             None => {
                 return Err(anyhow!(
                     "No setcode was specified on start-up, nor passed along in the input."
@@ -69,6 +193,25 @@ pub fn parse_addition_input(input: String, provided_set_code: Option<String>) ->
     Ok(res)
 }
 
+/// The single letter finish suffixes on collector numbers and card names.
+fn finish_suffix(suffix: &str) -> Option<Finish> {
+    match suffix {
+        "f" => Some(Finish::Foil),
+        "e" => Some(Finish::Etched),
+        "g" => Some(Finish::Gilded),
+        "t" => Some(Finish::Textured),
+        _ => None,
+    }
+}
+
+fn parse_quantity(quantity: &str) -> Result<u32> {
+    match quantity.parse::<u32>() {
+        Ok(0) => Err(anyhow!("Quantity has to be at least 1.")),
+        Ok(n) => Ok(n),
+        Err(_) => Err(anyhow!("Quantity is not a number: {quantity}")),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -80,16 +223,24 @@ mod test {
         let second_input = String::from("1");
 
         let expected = Input {
+            card_name: None,
             card_number: "1".to_string(),
             set_code: "blb".to_string(),
-            foil: false,
+            finish: Finish::Nonfoil,
+            language: "en".to_string(),
+            condition: Condition::NearMint,
             removal: false,
+            count: 1,
         };
         let second_expected = Input {
+            card_name: None,
             card_number: "1".to_string(),
             set_code: "dsk".to_string(),
-            foil: false,
+            finish: Finish::Nonfoil,
+            language: "en".to_string(),
+            condition: Condition::NearMint,
             removal: false,
+            count: 1,
         };
 
         let res = parse_addition_input(input, Some("dsk".to_string())).unwrap();
@@ -103,10 +254,14 @@ mod test {
     fn test_simple_input_provided_setcode() {
         let input = String::from("1");
         let expected = Input {
+            card_name: None,
             card_number: "1".to_string(),
             set_code: "blb".to_string(),
-            foil: false,
+            finish: Finish::Nonfoil,
+            language: "en".to_string(),
+            condition: Condition::NearMint,
             removal: false,
+            count: 1,
         };
 
         let res = parse_addition_input(input, Some("blb".to_string())).unwrap();
@@ -118,10 +273,14 @@ mod test {
     fn test_simple_with_set() {
         let input = "1 dsk".to_string();
         let expected = Input {
+            card_name: None,
             card_number: "1".to_string(),
             set_code: "dsk".to_string(),
-            foil: false,
+            finish: Finish::Nonfoil,
+            language: "en".to_string(),
+            condition: Condition::NearMint,
             removal: false,
+            count: 1,
         };
 
         let res = parse_addition_input(input, None).unwrap();
@@ -132,10 +291,14 @@ mod test {
     fn test_removal_input() {
         let input = "-2 dsk".to_string();
         let expected = Input {
+            card_name: None,
             card_number: 2.to_string(),
             set_code: "dsk".to_string(),
-            foil: false,
+            finish: Finish::Nonfoil,
+            language: "en".to_string(),
+            condition: Condition::NearMint,
             removal: true,
+            count: 1,
         };
         let res = parse_addition_input(input, None).unwrap();
         assert_eq!(res, expected)
@@ -145,10 +308,14 @@ mod test {
     fn test_simple_foil_input() {
         let input = "1f".to_string();
         let expected = Input {
+            card_name: None,
             card_number: "1".to_string(),
             set_code: "blb".to_string(),
-            foil: true,
+            finish: Finish::Foil,
+            language: "en".to_string(),
+            condition: Condition::NearMint,
             removal: false,
+            count: 1,
         };
         let res = parse_addition_input(input, Some("blb".to_string())).unwrap();
         assert_eq!(res, expected)
@@ -159,4 +326,143 @@ mod test {
         assert!(parse_addition_input("1 -dsk".to_string(), None).is_err());
         assert!(parse_addition_input("1-f".to_string(), None).is_err());
     }
+
+    #[test]
+    fn test_missing_collector_number() {
+        let dsk = || Some("dsk".to_string());
+        assert!(parse_addition_input("-".to_string(), dsk()).is_err());
+        assert!(parse_addition_input("3x".to_string(), dsk()).is_err());
+        assert!(parse_addition_input("- dsk".to_string(), None).is_err());
+        assert!(parse_addition_input("2x dsk".to_string(), None).is_err());
+    }
+
+    #[test]
+    fn test_quantity_prefix() {
+        let expected = Input {
+            card_name: None,
+            card_number: "12".to_string(),
+            set_code: "dsk".to_string(),
+            finish: Finish::Foil,
+            language: "en".to_string(),
+            condition: Condition::NearMint,
+            removal: false,
+            count: 4,
+        };
+        let res = parse_addition_input("4x12f dsk".to_string(), None).unwrap();
+        assert_eq!(res, expected);
+
+        let res = parse_addition_input("-3x12".to_string(), Some("dsk".to_string())).unwrap();
+        assert!(res.removal);
+        assert_eq!(res.count, 3);
+        assert_eq!(res.card_number, "12");
+    }
+
+    #[test]
+    fn test_quantity_suffix() {
+        let expected = Input {
+            card_name: None,
+            card_number: "12".to_string(),
+            set_code: "dsk".to_string(),
+            finish: Finish::Nonfoil,
+            language: "en".to_string(),
+            condition: Condition::NearMint,
+            removal: false,
+            count: 4,
+        };
+        let res = parse_addition_input("12 dsk *4".to_string(), None).unwrap();
+        assert_eq!(res, expected);
+        let res = parse_addition_input("12 *4".to_string(), Some("dsk".to_string())).unwrap();
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn test_invalid_quantities() {
+        assert!(parse_addition_input("0x12 dsk".to_string(), None).is_err());
+        assert!(parse_addition_input("12 dsk *0".to_string(), None).is_err());
+        assert!(parse_addition_input("12 dsk *a".to_string(), None).is_err());
+        assert!(parse_addition_input("2x12 dsk *4".to_string(), None).is_err());
+        assert!(parse_addition_input("12 dsk *2 *4".to_string(), None).is_err());
+        assert!(parse_addition_input("*4".to_string(), Some("dsk".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_name_input() {
+        let expected = Input {
+            card_number: String::new(),
+            card_name: Some("lightning bolt".to_string()),
+            set_code: "m11".to_string(),
+            finish: Finish::Nonfoil,
+            language: "en".to_string(),
+            condition: Condition::NearMint,
+            removal: false,
+            count: 1,
+        };
+        let res = parse_addition_input("\"lightning bolt\" m11".to_string(), None).unwrap();
+        assert_eq!(res, expected);
+
+        let res = parse_addition_input("=bolt".to_string(), None).unwrap();
+        assert_eq!(res.card_name, Some("bolt".to_string()));
+        assert_eq!(res.set_code, "");
+
+        let res = parse_addition_input("=bolt".to_string(), Some("m11".to_string())).unwrap();
+        assert_eq!(res.set_code, "m11");
+    }
+
+    #[test]
+    fn test_name_input_with_modifiers() {
+        let res = parse_addition_input("-2x\"lightning bolt\"f m11".to_string(), None).unwrap();
+        assert_eq!(res.card_name, Some("lightning bolt".to_string()));
+        assert!(res.removal);
+        assert_eq!(res.finish, Finish::Foil);
+        assert_eq!(res.count, 2);
+        assert_eq!(res.set_code, "m11");
+
+        let res = parse_addition_input("=\"fatal push\" *3".to_string(), None).unwrap();
+        assert_eq!(res.card_name, Some("fatal push".to_string()));
+        assert_eq!(res.finish, Finish::Nonfoil);
+        assert_eq!(res.count, 3);
+        assert_eq!(res.set_code, "");
+    }
+
+    #[test]
+    fn test_invalid_name_input() {
+        assert!(parse_addition_input("\"lightning bolt m11".to_string(), None).is_err());
+        assert!(parse_addition_input("=".to_string(), None).is_err());
+        assert!(parse_addition_input("\"\" m11".to_string(), None).is_err());
+    }
+
+    #[test]
+    fn test_finish_suffixes() {
+        let finish = |input: &str| {
+            parse_addition_input(input.to_string(), Some("dsk".to_string()))
+                .unwrap()
+                .finish
+        };
+        assert_eq!(finish("12e"), Finish::Etched);
+        assert_eq!(finish("12g"), Finish::Gilded);
+        assert_eq!(finish("12t"), Finish::Textured);
+        assert_eq!(finish("12 +surge"), Finish::Surge);
+        assert_eq!(finish("\"fatal push\"e"), Finish::Etched);
+        assert!(parse_addition_input("12f dsk +etched".to_string(), None).is_err());
+        assert!(parse_addition_input("12 dsk +shiny".to_string(), None).is_err());
+    }
+
+    #[test]
+    fn test_language_and_condition() {
+        let res = parse_addition_input("12 dsk ja @lp".to_string(), None).unwrap();
+        assert_eq!(res.set_code, "dsk");
+        assert_eq!(res.language, "ja");
+        assert_eq!(res.condition, Condition::LightlyPlayed);
+
+        let res =
+            parse_addition_input("12f zhs @dmg".to_string(), Some("dsk".to_string())).unwrap();
+        assert_eq!(res.set_code, "dsk");
+        assert_eq!(res.finish, Finish::Foil);
+        assert_eq!(res.language, "zhs");
+        assert_eq!(res.condition, Condition::Damaged);
+
+        assert!(parse_addition_input("12 dsk @mint-ish".to_string(), None).is_err());
+        assert!(parse_addition_input("12 dsk ja de".to_string(), None).is_err());
+        assert!(parse_addition_input("12 dsk blb".to_string(), None).is_err());
+    }
 }
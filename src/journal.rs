@@ -0,0 +1,223 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use anyhow::anyhow;
+
+use crate::types::{Card, Condition, Finish};
+
+/// A record of every change an `add` session made to its archive, so that
+/// mis-typed cards can be taken back with `:undo`, and brought back again with
+/// `:redo`.
+#[derive(Debug, Default)]
+pub struct Journal {
+    entries: Vec<JournalEntry>,
+    /// How many entries, counted from the end, are currently undone.
+    undone: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    pub card: Card,
+    pub removal: bool,
+}
+
+impl JournalEntry {
+    pub fn describe(&self) -> String {
+        let mut description = format!(
+            "{}{} {} ({}) {}",
+            if self.removal { "-" } else { "+" },
+            self.card.count,
+            self.card.name,
+            self.card.set.to_ascii_uppercase(),
+            self.card.collector_number,
+        );
+        if self.card.finish != Finish::Nonfoil {
+            description.push_str(&format!(" {}", self.card.finish.deck_list_marker()));
+        }
+        if self.card.language != "en" {
+            description.push_str(&format!(" [{}]", self.card.language));
+        }
+        if self.card.condition != Condition::NearMint {
+            description.push_str(&format!(" @{}", self.card.condition.code()));
+        }
+        description
+    }
+}
+
+impl Journal {
+    /// Records a successful `edit_archive` call. Anything that was undone
+    /// before can't be redone after this anymore.
+    pub fn record(&mut self, card: Card, removal: bool) {
+        self.entries.truncate(self.entries.len() - self.undone);
+        self.undone = 0;
+        self.entries.push(JournalEntry { card, removal });
+    }
+
+    /// The entry that `:undo` would revert next.
+    pub fn next_undo(&self) -> Option<&JournalEntry> {
+        let applied = self.entries.len() - self.undone;
+        applied.checked_sub(1).map(|i| &self.entries[i])
+    }
+
+    /// The entry that `:redo` would apply next.
+    pub fn next_redo(&self) -> Option<&JournalEntry> {
+        match self.undone {
+            0 => None,
+            n => Some(&self.entries[self.entries.len() - n]),
+        }
+    }
+
+    /// Marks `next_undo()` as undone. Call after reverting it in the archive.
+    pub fn undo(&mut self) {
+        if self.next_undo().is_some() {
+            self.undone += 1;
+        }
+    }
+
+    /// Marks `next_redo()` as applied. Call after re-applying it to the archive.
+    pub fn redo(&mut self) {
+        if self.next_redo().is_some() {
+            self.undone -= 1;
+        }
+    }
+
+    /// All entries in the order they were made, along with whether they are
+    /// currently undone.
+    pub fn history(&self) -> impl Iterator<Item = (&JournalEntry, bool)> {
+        let applied = self.entries.len() - self.undone;
+        self.entries
+            .iter()
+            .enumerate()
+            .map(move |(i, entry)| (entry, i >= applied))
+    }
+}
+
+/// Reverts `entry` in the archive at `path`. An undone addition that leaves no
+/// copies behind removes the card from the archive again, instead of leaving
+/// an entry with a count of zero.
+pub fn revert(entry: &JournalEntry, path: Option<PathBuf>) -> Result<u32> {
+    if entry.removal {
+        return crate::edit_archive(entry.card.clone(), path, false);
+    }
+
+    crate::modify_archive(path, |a| {
+        let Some(index) = a.iter().position(|c| c.is_same_printing(&entry.card)) else {
+            return Err(anyhow!(
+                "Can't undo, {} is not in the collection anymore.",
+                entry.card.name
+            ));
+        };
+        if a[index].count < entry.card.count {
+            return Err(anyhow!(
+                "Can't undo, only {} copies of {} are left in the collection.",
+                a[index].count,
+                entry.card.name
+            ));
+        }
+        a[index].count -= entry.card.count;
+        a[index].clamp_allocations();
+        let count = a[index].count;
+        if count == 0 {
+            a.remove(index);
+        }
+        Ok(count)
+    })
+}
+
+/// Applies `entry` to the archive at `path` again.
+pub fn reapply(entry: &JournalEntry, path: Option<PathBuf>) -> Result<u32> {
+    crate::edit_archive(entry.card.clone(), path, entry.removal)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::fixture_card;
+
+    #[test]
+    fn test_undo_redo() {
+        let mut journal = Journal::default();
+        assert!(journal.next_undo().is_none());
+
+        journal.record(fixture_card("1"), false);
+        journal.record(fixture_card("2"), true);
+        assert_eq!(journal.next_undo().unwrap().card.collector_number, "2");
+        assert!(journal.next_redo().is_none());
+
+        journal.undo();
+        assert_eq!(journal.next_undo().unwrap().card.collector_number, "1");
+        assert_eq!(journal.next_redo().unwrap().card.collector_number, "2");
+
+        journal.undo();
+        assert!(journal.next_undo().is_none());
+        journal.undo();
+        assert_eq!(journal.next_redo().unwrap().card.collector_number, "1");
+
+        journal.redo();
+        assert_eq!(journal.next_undo().unwrap().card.collector_number, "1");
+        assert_eq!(journal.next_redo().unwrap().card.collector_number, "2");
+    }
+
+    #[test]
+    fn test_record_drops_redo() {
+        let mut journal = Journal::default();
+        journal.record(fixture_card("1"), false);
+        journal.record(fixture_card("2"), false);
+        journal.undo();
+        journal.record(fixture_card("3"), false);
+
+        assert!(journal.next_redo().is_none());
+        let history: Vec<_> = journal
+            .history()
+            .map(|(entry, undone)| (entry.card.collector_number.clone(), undone))
+            .collect();
+        assert_eq!(
+            history,
+            vec![("1".to_string(), false), ("3".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn test_history_marks_undone() {
+        let mut journal = Journal::default();
+        journal.record(fixture_card("1"), false);
+        journal.record(fixture_card("2"), true);
+        journal.undo();
+
+        let history: Vec<_> = journal.history().map(|(_, undone)| undone).collect();
+        assert_eq!(history, vec![false, true]);
+        assert_eq!(
+            journal.history().nth(1).unwrap().0.describe(),
+            "-1 Card 2 (BLB) 2"
+        );
+    }
+
+    #[test]
+    fn test_revert_frees_allocations() {
+        let dir = std::env::temp_dir().join(format!(
+            "crackathon-test-{}-revert-allocations",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("collection.json");
+
+        let mut owned = Card {
+            count: 3,
+            ..fixture_card("1")
+        };
+        owned.allocations.insert("burn".to_string(), 3);
+        crate::edit_archive(owned, Some(path.clone()), false).unwrap();
+
+        let added = JournalEntry {
+            card: Card {
+                count: 2,
+                ..fixture_card("1")
+            },
+            removal: false,
+        };
+        assert_eq!(revert(&added, Some(path.clone())).unwrap(), 1);
+        let crate::Archive(cards) = crate::read_collection(Some(path)).unwrap();
+        assert_eq!(cards[0].allocated_to("burn"), 1);
+    }
+}
@@ -1,20 +1,47 @@
 use anyhow::Result;
 use anyhow::anyhow;
-use clap::{Parser, Subcommand, ValueEnum};
-use reqwest::{blocking, header};
-use rustyline::DefaultEditor;
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use itertools::Itertools;
+use rustyline::Editor;
+use rustyline::history::DefaultHistory;
 use types::OldArchive;
 use types::State;
 
-use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
+mod alloc;
+mod browse;
+mod check;
+mod completion;
+mod config;
+mod csv;
+mod db;
+mod export;
+mod import;
 mod input_parser;
+mod journal;
+mod picker;
+mod progress;
 mod scryfall;
+mod search;
+mod stats;
 mod types;
+mod validate;
+mod value;
 
-use types::{Archive, Card};
+use completion::CardNameCompleter;
+use config::Config;
+use db::CardDb;
+use export::{DeckList, ExportType, Exporter};
+use input_parser::Input;
+use journal::Journal;
+use scryfall::NameMatch;
+use types::{Archive, Card, Currency, Section};
+use value::PriceSnapshot;
+
+type AddEditor = Editor<CardNameCompleter, DefaultHistory>;
 
 fn main() -> Result<()> {
     let args = Options::parse();
@@ -24,17 +51,64 @@ fn main() -> Result<()> {
             output,
             input,
             format,
-        }) => command_export(input, output, format)?,
+            columns,
+            free,
+            allocated_to,
+        }) => command_export(input, output, format, columns, free, allocated_to)?,
         Some(Commands::Add {
             output_file,
             set_code,
         }) => command_add(output_file, set_code)?,
-        Some(Commands::CollectionPath) => println!("{}", archive_collection_path().display()),
-        //        Some(Commands::Search { path }) => command_search(path)?,
-        Some(Commands::Create { name, set_used }) => command_list_create(name, set_used)?,
+        Some(Commands::Import {
+            file,
+            format,
+            output,
+            report,
+        }) => command_import(file, format, output, report)?,
+        Some(Commands::Check {
+            deck,
+            collection,
+            want_list,
+            exact,
+        }) => command_check(deck, collection, want_list, exact)?,
+        Some(Commands::Validate { deck, format }) => command_validate(deck, format)?,
+        Some(Commands::Value { path, top, fetch }) => command_value(path, top, fetch)?,
+        Some(Commands::SetProgress {
+            set,
+            playset,
+            collection,
+            want_list,
+        }) => command_set_progress(set, playset, collection, want_list)?,
+        Some(Commands::Stats { path, chart }) => command_stats(path, chart)?,
+        Some(Commands::CollectionPath) => println!("{}", archive_collection_path()?.display()),
+        Some(Commands::Search { path, query }) => command_search(path, query)?,
+        Some(Commands::Browse { path }) => browse::run(path)?,
+        Some(Commands::Create {
+            name,
+            set_used,
+            force,
+            from,
+            template,
+        }) => command_list_create(name, set_used, force, from, template)?,
         Some(Commands::List { subcommand }) => match subcommand {
-            ListCommands::Create { name, set_used } => command_list_create(name, set_used)?,
+            ListCommands::Create {
+                name,
+                set_used,
+                force,
+                from,
+                template,
+            } => command_list_create(name, set_used, force, from, template)?,
             ListCommands::Use { path } => command_list_use(path)?,
+            ListCommands::Ls => command_list_ls()?,
+            ListCommands::Show { name } => command_list_show(name)?,
+            ListCommands::Delete { name, yes } => command_list_delete(name, yes)?,
+            ListCommands::Rename { from, to } => command_list_rename(from, to)?,
+            ListCommands::Copy { from, to } => command_list_copy(from, to)?,
+        },
+        Some(Commands::Alloc { subcommand }) => command_alloc(subcommand)?,
+        Some(Commands::Config { subcommand }) => command_config(subcommand)?,
+        Some(Commands::Db { subcommand }) => match subcommand {
+            DbCommands::Import { file } => command_db_import(file)?,
         },
         _ => {}
     }
@@ -63,9 +137,19 @@ enum Commands {
         /// Which file to read from.
         #[arg(short, long, value_name = "INPUT_FILE")]
         input: Option<PathBuf>,
-        /// Export as either Decklist, or CSV format.
+        /// Which format to export as.
         #[arg(short, long, value_enum)]
         format: Option<ExportType>,
+        /// Which columns to write, and in what order, for the csv format.
+        /// Defaults to all of them.
+        #[arg(long, value_enum, value_delimiter = ',', num_args = 1..)]
+        columns: Option<Vec<export::Column>>,
+        /// Only export copies that aren't allocated to a deck, see `alloc`.
+        #[arg(long, conflicts_with = "allocated_to")]
+        free: bool,
+        /// Only export copies allocated to this deck, see `alloc`.
+        #[arg(long, value_name = "DECK_NAME")]
+        allocated_to: Option<String>,
     },
     /// Add some cards to a collection.
     Add {
@@ -76,11 +160,99 @@ enum Commands {
         #[arg(short, long, value_name = "SET_CODE")]
         set_code: Option<String>,
     },
+    /// Import a deck list or a Moxfield CSV export into a collection.
+    Import {
+        /// The file to import.
+        #[arg(value_name = "INPUT_FILE")]
+        file: PathBuf,
+        /// Import as either Decklist, or CSV format.
+        #[arg(short, long, value_enum, default_value = "deck")]
+        format: ImportType,
+        /// Which collection to import into. If not specified, imports into the current list.
+        #[arg(short, long, value_name = "OUTPUT_FILE")]
+        output: Option<PathBuf>,
+        /// Where to write lines that could not be imported. Defaults to
+        /// `<INPUT_FILE>-unresolved.txt` next to the input.
+        #[arg(short, long, value_name = "REPORT_FILE")]
+        report: Option<PathBuf>,
+    },
+    /// Check which cards of a deck are in the collection, and which are missing.
+    Check {
+        /// The deck: a list name, an archive, or a deck list or Moxfield CSV file.
+        #[arg(value_name = "DECK")]
+        deck: String,
+        /// Which collection to check against. Defaults to the `collection` list.
+        #[arg(short, long, value_name = "COLLECTION_FILE")]
+        collection: Option<PathBuf>,
+        /// Write the missing cards to this file, as a deck list.
+        #[arg(short = 'o', long, value_name = "OUTPUT_FILE")]
+        want_list: Option<PathBuf>,
+        /// Count cards only owned in another printing as missing.
+        #[arg(long)]
+        exact: bool,
+    },
+    /// Check a deck against the deck construction rules of a format.
+    Validate {
+        /// The deck: a list name, an archive, or a deck list or Moxfield CSV file.
+        #[arg(value_name = "DECK")]
+        deck: String,
+        /// Which format's rules to check against.
+        #[arg(short, long, value_enum)]
+        format: validate::Format,
+    },
+    /// Re-price a collection, and report its value and how it changed since last time.
+    Value {
+        /// Which file to value. If not specified, values the current list.
+        #[arg(value_name = "INPUT_FILE")]
+        path: Option<PathBuf>,
+        /// How many of the most valuable cards to list.
+        #[arg(short = 'n', long, default_value = "10")]
+        top: usize,
+        /// Fetch all prices from Scryfall, even for cards in the local card
+        /// database, whose prices are as old as its bulk file.
+        #[arg(long)]
+        fetch: bool,
+    },
+    /// Show which cards of a set are missing from the collection.
+    SetProgress {
+        /// The set's code, ie `blb`.
+        #[arg(value_name = "SET_CODE")]
+        set: String,
+        /// Count a printing as complete only with 4 copies, instead of 1.
+        #[arg(long)]
+        playset: bool,
+        /// Which collection to compare against. Defaults to the `collection` list.
+        #[arg(short, long, value_name = "COLLECTION_FILE")]
+        collection: Option<PathBuf>,
+        /// Write the missing cards to this file, as a deck list.
+        #[arg(short = 'o', long, value_name = "OUTPUT_FILE")]
+        want_list: Option<PathBuf>,
+    },
+    /// Show statistics of a collection: copies by set, rarity, color, finish
+    /// and price, and how complete each set is.
+    Stats {
+        /// Which file to summarize. If not specified, summarizes the current list.
+        #[arg(value_name = "INPUT_FILE")]
+        path: Option<PathBuf>,
+        /// Show the statistics as interactive bar charts.
+        #[arg(long)]
+        chart: bool,
+    },
     /// Dump the default collection path. Useful for scripting.
     CollectionPath,
-    /// Search the specified collection.
+    /// Search the specified collection, using a subset of Scryfall's query syntax.
     Search {
-        #[arg()]
+        /// Which file to search. If not specified, searches the current list.
+        #[arg(short = 'i', long = "input", value_name = "INPUT_FILE")]
+        path: Option<PathBuf>,
+        /// The query, e.g. `set:blb r>=rare -foil` or `c:ug or usd>1.00`.
+        #[arg(required = true, num_args = 1..)]
+        query: Vec<String>,
+    },
+    /// Browse and edit a collection in an interactive table.
+    Browse {
+        /// Which file to browse. If not specified, browses the current list.
+        #[arg(value_name = "INPUT_FILE")]
         path: Option<PathBuf>,
     },
     /// Manipulate decklists and collections.
@@ -94,14 +266,56 @@ enum Commands {
         #[arg(short, long, value_name = "DECK_NAME")]
         name: String,
         /// Whether or not to set the decklist as the currently active default collection. Defaults to true.
-        #[arg(short, long, default_value = "true")]
+        #[arg(short, long, default_value_t = true, action = ArgAction::Set)]
         set_used: bool,
+        /// Replace the list if it already exists. The old one is kept as a backup.
+        #[arg(long)]
+        force: bool,
+        /// Start out with a copy of this list.
+        #[arg(long, value_name = "DECK_NAME", conflicts_with = "template")]
+        from: Option<String>,
+        /// Start out with the cards of this deck list file, see `import`.
+        #[arg(long, value_name = "DECK_LIST")]
+        template: Option<PathBuf>,
+    },
+    /// Track which copies of the collection are sleeved in which deck.
+    Alloc {
+        #[command(subcommand)]
+        subcommand: AllocCommands,
+    },
+    /// Manage the local copy of Scryfall's card data.
+    Db {
+        #[command(subcommand)]
+        subcommand: DbCommands,
+    },
+    /// Change the crackathon configuration.
+    Config {
+        #[command(subcommand)]
+        subcommand: ConfigCommands,
     },
-    // /// Change the crackathon configuration.
-    // Config {
-    //     #[arg(long)]
-    //     set_home: String,
-    // },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the value of a setting.
+    Get {
+        #[arg(value_name = "KEY")]
+        key: String,
+    },
+    /// Change a setting.
+    Set {
+        #[arg(value_name = "KEY")]
+        key: String,
+        #[arg(value_name = "VALUE")]
+        value: String,
+    },
+    /// Reset a setting to its default.
+    Unset {
+        #[arg(value_name = "KEY")]
+        key: String,
+    },
+    /// Print all settings, and where the config file is.
+    List,
 }
 
 #[derive(Subcommand)]
@@ -112,46 +326,126 @@ enum ListCommands {
         #[arg(short, long, value_name = "DECK_NAME")]
         name: String,
         /// Whether or not to set the decklist as the currently active default collection. Defaults to true.
-        #[arg(short, long, default_value = "true")]
+        #[arg(short, long, default_value_t = true, action = ArgAction::Set)]
         set_used: bool,
+        /// Replace the list if it already exists. The old one is kept as a backup.
+        #[arg(long)]
+        force: bool,
+        /// Start out with a copy of this list.
+        #[arg(long, value_name = "DECK_NAME", conflicts_with = "template")]
+        from: Option<String>,
+        /// Start out with the cards of this deck list file, see `import`.
+        #[arg(long, value_name = "DECK_LIST")]
+        template: Option<PathBuf>,
     },
-    /// Set a new list as "current". Opens a selector if not given a path.
+    /// Set a new list as "current". Opens a selector if not given a name.
+    /// An absolute path uses a list outside of the usual directory.
     Use {
         #[arg(value_name = "DECK_NAME")]
         path: Option<String>,
     },
-    // /// Delete a decklist. Opens a selector if not given a path.
-    // Delete {
-    //     #[arg(value_name = "DECK_PATH")]
-    //     path: Option<String>,
-    // },
-    // /// Prints a decklist. Prints currently used decklist if not given a path.
-    // /// Optionally opens selector.
-    // Show {
-    //     #[arg(value_name = "DECK_PATH")]
-    //     path: Option<String>,
-    //     #[arg(short, long)]
-    //     select: bool,
-    // },
+    /// Show all lists, with their card count and value. The current list is marked with `*`.
+    Ls,
+    /// Prints a decklist. Prints the currently used list if not given a name.
+    Show {
+        #[arg(value_name = "DECK_NAME")]
+        name: Option<String>,
+    },
+    /// Delete a decklist. Its backups are kept.
+    Delete {
+        #[arg(value_name = "DECK_NAME")]
+        name: String,
+        /// Don't ask for confirmation.
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Rename a decklist.
+    Rename {
+        #[arg(value_name = "DECK_NAME")]
+        from: String,
+        #[arg(value_name = "NEW_NAME")]
+        to: String,
+    },
+    /// Copy a decklist to a new one.
+    Copy {
+        #[arg(value_name = "DECK_NAME")]
+        from: String,
+        #[arg(value_name = "NEW_NAME")]
+        to: String,
+    },
+}
+
+/// Cards are given as deck list lines, ie `"4 Lightning Bolt (M11) 149"`, of
+/// which only the count, name, set and collector number are considered.
+#[derive(Subcommand)]
+enum AllocCommands {
+    /// Allocate free copies to a deck. Without cards, allocates what the list
+    /// DECK_NAME needs, replacing what was allocated to it before.
+    Add {
+        #[arg(value_name = "DECK_NAME")]
+        deck: String,
+        #[arg(value_name = "CARD")]
+        cards: Vec<String>,
+    },
+    /// Free copies allocated to a deck, all of them without cards.
+    Remove {
+        #[arg(value_name = "DECK_NAME")]
+        deck: String,
+        #[arg(value_name = "CARD")]
+        cards: Vec<String>,
+    },
+    /// Move copies from one deck to another, all of them without cards.
+    Move {
+        #[arg(value_name = "FROM")]
+        from: String,
+        #[arg(value_name = "TO")]
+        to: String,
+        #[arg(value_name = "CARD")]
+        cards: Vec<String>,
+    },
+    /// Show how many copies are free and allocated to each deck, or which
+    /// cards are allocated to DECK_NAME.
+    Ls {
+        #[arg(value_name = "DECK_NAME")]
+        deck: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbCommands {
+    /// Import a Scryfall bulk data file, ie "Default Cards" from
+    /// https://scryfall.com/docs/api/bulk-data. Replaces the current database.
+    Import {
+        #[arg(value_name = "BULK_FILE")]
+        file: PathBuf,
+    },
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
-enum ExportType {
+enum ImportType {
     Deck,
     Csv,
 }
 
 fn command_add(output: Option<PathBuf>, set_code: Option<String>) -> Result<()> {
-    let mut headers = header::HeaderMap::new();
-    headers.insert(header::ACCEPT, "application/json".parse().unwrap());
-    let client = blocking::ClientBuilder::new()
-        .user_agent("Crack-a-thon, see github.com/MordecaiMalignatus/archivist.")
-        .default_headers(headers)
-        .build()?;
-    let mut rl = DefaultEditor::new()?;
+    let set_code = set_code.or(Config::load()?.default_set);
+    let client = scryfall_client()?;
+    let db = CardDb::open(card_db_path()?);
+    let mut journal = Journal::default();
+    let Archive(existing_cards) = read_collection(output.clone())?;
+    let mut rl = AddEditor::new()?;
+    rl.set_helper(Some(CardNameCompleter::new(
+        existing_cards.into_iter().map(|c| c.name),
+    )));
 
+    let into_collection = is_collection(output.as_deref())?;
+    let mut section = Section::Mainboard;
     loop {
-        let buffer = rl.readline("Enter Card Number: ")?;
+        let prompt = match section {
+            Section::Mainboard => "Enter Card Number: ".to_string(),
+            section => format!("Enter Card Number ({}): ", section.header().to_lowercase()),
+        };
+        let buffer = rl.readline(&prompt)?;
         let buffer = buffer.trim().to_string();
         rl.add_history_entry(buffer.as_str())?;
 
@@ -159,6 +453,18 @@ fn command_add(output: Option<PathBuf>, set_code: Option<String>) -> Result<()>
             println!("Empty input received, exiting...");
             break;
         }
+        if let Some(meta_command) = buffer.strip_prefix(':') {
+            if let Err(e) = command_add_meta(
+                meta_command,
+                &mut journal,
+                &mut section,
+                into_collection,
+                output.clone(),
+            ) {
+                eprintln!("{e}");
+            }
+            continue;
+        }
         let parsed_input = match input_parser::parse_addition_input(buffer, set_code.clone()) {
             Ok(parsed) => parsed,
             Err(e) => {
@@ -167,18 +473,34 @@ fn command_add(output: Option<PathBuf>, set_code: Option<String>) -> Result<()>
             }
         };
 
-        let mut card = match scryfall::query_card(
-            &parsed_input.set_code,
-            &parsed_input.card_number,
-            &client,
-        ) {
-            Ok(card) => card,
+        let mut card = match resolve_card(&parsed_input, &client, &db, &mut rl) {
+            Ok(Some(card)) => card,
+            Ok(None) => continue,
             Err(e) => {
                 eprintln!("Error from scryfall: {e}");
                 continue;
             }
         };
-        card.foil = parsed_input.foil;
+        card.finish = parsed_input.finish;
+        card.language = parsed_input.language.clone();
+        card.condition = parsed_input.condition;
+        card.count = parsed_input.count;
+        card.section = section;
+
+        if !parsed_input.removal && card.is_available_in(card.finish) == Some(false) {
+            let available = card.finishes.iter().map(|f| f.name()).join(", ");
+            let question = format!(
+                "{} ({}) {} was never printed in {}, only in {available}. Add it anyway?",
+                card.name,
+                card.set.to_ascii_uppercase(),
+                card.collector_number,
+                card.finish.name(),
+            );
+            if !confirm(&question, |prompt| Ok(rl.readline(prompt)?))? {
+                println!("Not added.\n");
+                continue;
+            }
+        }
 
         let resulting_count = match edit_archive(card.clone(), output.clone(), parsed_input.removal)
         {
@@ -188,34 +510,38 @@ fn command_add(output: Option<PathBuf>, set_code: Option<String>) -> Result<()>
                 continue;
             }
         };
+        journal.record(card.clone(), parsed_input.removal);
+        if let Some(completer) = rl.helper_mut() {
+            completer.insert(card.name.clone());
+        }
+        let copies = match card.count {
+            1 => card.name.clone(),
+            n => format!("{n}x {}", card.name),
+        };
         let modification_text = match parsed_input.removal {
             true => match resulting_count {
-                0 => format!("Removed {} from collection!\n", card.name),
+                0 => format!("Removed {copies} from collection!\n"),
                 _ => format!(
-                    "Removed {} from collection! ({resulting_count} remaining in this collection)\n",
-                    card.name
+                    "Removed {copies} from collection! ({resulting_count} remaining in this collection)\n"
                 ),
             },
             false => {
-                let price_string = match card.prices {
-                    Some(prices) => match card.foil {
-                        true => {
-                            // TODO(sar): this is not always true, mis-input happens, I should care for that right.
-                            format!(
-                                "({}€ / ${})",
-                                prices.eur_foil.unwrap(),
-                                prices.usd_foil.unwrap()
-                            )
-                        }
-                        false => format!("({}€ / ${})", prices.eur, prices.usd),
-                    },
-                    None => "".to_string(),
+                let price_string = match (card.eur(), card.usd()) {
+                    (Some(eur), Some(usd)) => format!("({eur} / {usd})"),
+                    (Some(price), None) | (None, Some(price)) => format!("({price})"),
+                    (None, None) => "".to_string(),
                 };
-                match resulting_count {
-                    1 => format!("Added {} to collection! {price_string}\n", card.name),
-                    c => format!(
-                        "Added {} to collection! ({c} in this collection) {price_string}\n",
+                if card.is_available_in(card.finish).is_none() && card.lacks_finish_price() {
+                    eprintln!(
+                        "Warning: Scryfall has no {} price for {}, it may not exist in that finish. Use :undo if that was a typo.",
+                        card.finish.name(),
                         card.name
+                    );
+                }
+                match resulting_count == card.count {
+                    true => format!("Added {copies} to collection! {price_string}\n"),
+                    false => format!(
+                        "Added {copies} to collection! ({resulting_count} in this collection) {price_string}\n"
                     ),
                 }
             }
@@ -226,21 +552,175 @@ fn command_add(output: Option<PathBuf>, set_code: Option<String>) -> Result<()>
     Ok(())
 }
 
-/// Export converts the current collection to the common format that is accepted
-/// by Arena, Moxfield et al. This format is roughly: "$AMOUNT $CARDNAME
-/// ($SETCODE)? $NUMBER? $FOIL?" Due to the internal structure of this
-/// application, the export is going to be sorted by set.
+/// Looks up the card `input` refers to, either by collector number or by name.
+/// If a name matches several cards, asks which one was meant. Returns `None` if
+/// that question was dismissed.
+fn resolve_card(
+    input: &Input,
+    client: &scryfall::Client,
+    db: &CardDb,
+    rl: &mut AddEditor,
+) -> Result<Option<Card>> {
+    let Some(name) = &input.card_name else {
+        return scryfall::query_card(&input.set_code, &input.card_number, client, db).map(Some);
+    };
+    let set = match input.set_code.is_empty() {
+        true => None,
+        false => Some(input.set_code.as_str()),
+    };
+
+    let mut name = name.clone();
+    loop {
+        match scryfall::query_card_by_name(&name, set, client, db)? {
+            NameMatch::Card(card) => return Ok(Some(*card)),
+            NameMatch::Ambiguous(names) => match pick(rl, &names)? {
+                Some(i) => name = names[i].clone(),
+                None => return Ok(None),
+            },
+            NameMatch::Printings(cards) => {
+                let options: Vec<String> = cards
+                    .iter()
+                    .map(|c| {
+                        format!(
+                            "{} ({}) {}",
+                            c.name,
+                            c.set.to_ascii_uppercase(),
+                            c.collector_number
+                        )
+                    })
+                    .collect();
+                return Ok(pick(rl, &options)?.map(|i| cards[i].clone()));
+            }
+        }
+    }
+}
+
+/// Prints `options` as a numbered list and asks for one of them. Returns the
+/// index of the chosen option, or `None` on empty input.
+fn pick(rl: &mut AddEditor, options: &[String]) -> Result<Option<usize>> {
+    for (i, option) in options.iter().enumerate() {
+        println!("{:>3}. {option}", i + 1);
+    }
+    loop {
+        let answer = rl.readline(&format!(
+            "Which one? (1-{}, empty to cancel): ",
+            options.len()
+        ))?;
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(None);
+        }
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=options.len()).contains(&n) => return Ok(Some(n - 1)),
+            _ => eprintln!("Expected a number between 1 and {}.", options.len()),
+        }
+    }
+}
+
+/// Asks a yes/no `question`, defaulting to no. `read_line` shows the prompt
+/// and reads the answer, ie with the `add` REPL's editor, or `read_stdin_line`.
+fn confirm(question: &str, read_line: impl FnOnce(&str) -> Result<String>) -> Result<bool> {
+    let answer = read_line(&format!("{question} [y/N]: "))?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// Handles the `:`-prefixed commands of the `add` REPL, which operate on the
+/// session's journal or switch the section of the deck that cards are added
+/// to, ie `:sb`, rather than adding cards. The collection isn't a deck, so it
+/// only has the mainboard.
+fn command_add_meta(
+    command: &str,
+    journal: &mut Journal,
+    section: &mut Section,
+    into_collection: bool,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    match command.trim() {
+        "undo" | "u" => {
+            let Some(entry) = journal.next_undo().cloned() else {
+                return Err(anyhow!("Nothing to undo."));
+            };
+            let count = journal::revert(&entry, output)?;
+            journal.undo();
+            println!(
+                "Undid `{}`. ({count} in this collection)\n",
+                entry.describe()
+            );
+        }
+        "redo" | "r" => {
+            let Some(entry) = journal.next_redo().cloned() else {
+                return Err(anyhow!("Nothing to redo."));
+            };
+            let count = journal::reapply(&entry, output)?;
+            journal.redo();
+            println!(
+                "Redid `{}`. ({count} in this collection)\n",
+                entry.describe()
+            );
+        }
+        "history" | "h" => {
+            for (i, (entry, undone)) in journal.history().enumerate() {
+                println!(
+                    "{:>3}. {}{}",
+                    i + 1,
+                    entry.describe(),
+                    if undone { " (undone)" } else { "" }
+                );
+            }
+            println!();
+        }
+        other => {
+            let Some(parsed) = Section::parse(other) else {
+                return Err(anyhow!(
+                    "Unknown command `:{other}`, expected one of :undo, :redo, :history, or a section like :main, :sb, :cmd or :maybe."
+                ));
+            };
+            if into_collection && !parsed.is_mainboard() {
+                return Err(anyhow!(
+                    "The collection has no {}, sections are only for decks.",
+                    parsed.header().to_lowercase()
+                ));
+            }
+            *section = parsed;
+            println!("Adding to the {}.\n", parsed.header().to_lowercase());
+        }
+    }
+    Ok(())
+}
+
+/// Export converts the current collection to a format other tools accept, see
+/// `ExportType`. Without a format, the configured default is used, or else the
+/// deck list format that Arena, Moxfield et al accept.
 fn command_export(
     input_path: Option<PathBuf>,
     output_path: Option<PathBuf>,
     format: Option<ExportType>,
+    columns: Option<Vec<export::Column>>,
+    free: bool,
+    allocated_to: Option<String>,
 ) -> Result<()> {
-    let Archive(a) = read_collection(input_path)?;
-    let output = match format {
-        Some(ExportType::Csv) => format_as_moxfield_csv(&a),
-        Some(ExportType::Deck) => format_as_deck_list(&a),
-        None => format_as_deck_list(&a),
+    let Archive(mut a) = read_collection(input_path)?;
+    if free {
+        a = alloc::free_copies(&a);
+    }
+    if let Some(deck) = allocated_to {
+        a = alloc::deck_copies(&a, &deck);
+    }
+    let format = match format {
+        Some(format) => format,
+        None => Config::load()?
+            .default_export_format
+            .unwrap_or(ExportType::Deck),
+    };
+    let exporter: Box<dyn Exporter> = match columns {
+        Some(columns) if format == ExportType::Csv => Box::new(export::Moxfield { columns }),
+        Some(_) => return Err(anyhow!("Columns can only be picked for the csv format.")),
+        None => format.exporter(),
     };
+    let output = exporter.export(&a);
 
     match output_path {
         Some(path) => {
@@ -253,117 +733,833 @@ fn command_export(
     Ok(())
 }
 
-/// Exports the decklist as a moxfield-compatible CSV. Documentation can be
-/// found here: https://moxfield.com/help/importing-collection
-fn format_as_moxfield_csv(archive: &[Card]) -> String {
-    let mut output = String::new();
-    output.push_str("\"Count\",\"Name\",\"Collector Number\",\"Edition\",\"Foil\"\n");
-
-    archive.iter().for_each(|card| {
-        let line = format!(
-            "\"{}\",\"{}\",\"{}\",\"{}\",\"{}\"\n",
-            card.count,
-            card.name,
-            card.collector_number,
-            card.set,
-            if card.foil { "foil" } else { "" },
+/// Imports the cards listed in `file` into the archive at `output`. Every
+/// line is looked up first, and all found cards are then merged into the
+/// archive in one go. Lines that can't be found are written to `report`, so
+/// they can be fixed up and imported again.
+fn command_import(
+    file: PathBuf,
+    format: ImportType,
+    output: Option<PathBuf>,
+    report: Option<PathBuf>,
+) -> Result<()> {
+    let content =
+        fs::read_to_string(&file).map_err(|e| anyhow!("Could not read {}: {e}", file.display()))?;
+    let (lines, mut unresolved) = match format {
+        ImportType::Deck => import::parse_deck_list(&content),
+        ImportType::Csv => import::parse_moxfield_csv(&content)?,
+    };
+
+    let client = scryfall_client()?;
+    let db = CardDb::open(card_db_path()?);
+    let (cards, unresolved_lookups) = import::resolve(lines, &client, &db);
+    unresolved.extend(unresolved_lookups);
+
+    let copies: u32 = cards.iter().map(|c| c.count).sum();
+    let printings = cards.len();
+    let into_collection = is_collection(output.as_deref())?;
+    modify_archive(output, |a| {
+        match into_collection {
+            true => import::merge_into_collection(a, cards),
+            false => import::merge(a, cards),
+        }
+        Ok(())
+    })?;
+    println!("Imported {copies} cards ({printings} lines).");
+
+    if !unresolved.is_empty() {
+        let report = report.unwrap_or_else(|| {
+            let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+            file.with_file_name(format!("{stem}-unresolved.txt"))
+        });
+        fs::write(&report, import::format_report(&unresolved))
+            .map_err(|e| anyhow!("Could not write report to {}: {e}", report.display()))?;
+        println!(
+            "{} lines could not be imported, see {}",
+            unresolved.len(),
+            report.display()
         );
-        output.push_str(&line);
-    });
+    }
+    Ok(())
+}
 
-    output
+/// Compares `deck` against the collection, and prints which of its cards are
+/// owned, owned in another printing, or missing. The missing ones can be
+/// written out as a want-list, in the same format `export` uses.
+fn command_check(
+    deck: String,
+    collection: Option<PathBuf>,
+    want_list: Option<PathBuf>,
+    exact: bool,
+) -> Result<()> {
+    // Copies allocated to the deck count as owned, if it's a list.
+    let deck_name = (!Path::new(&deck).is_file()).then_some(deck.as_str());
+    let cards = read_deck(&deck)?;
+    let collection = match collection {
+        Some(path) => path,
+        None => archive_collection_path()?,
+    };
+    let Archive(collection) = read_collection(Some(collection))?;
+
+    let availability = check::check(&cards, &collection, deck_name);
+    print!("{}", check::format_report(&availability));
+
+    if let Some(path) = want_list {
+        let mut wanted = availability.missing;
+        if exact {
+            wanted.extend(availability.other_printing.into_iter().map(|(c, _)| c));
+        }
+        fs::write(&path, DeckList.export(&wanted))
+            .map_err(|e| anyhow!("Could not write want-list to {}: {e}", path.display()))?;
+        println!("Wrote want-list to {}", path.display());
+    }
+    Ok(())
 }
 
-fn format_as_deck_list(archive: &[Card]) -> String {
-    let mut output = String::new();
+/// Checks `deck` against the rules of `format`, and prints every rule it
+/// breaks. Cards from before legalities were recorded are looked up again
+/// first, see `look_up_printings`. Fails if the deck isn't legal, so it can be
+/// used in scripts.
+fn command_validate(deck: String, format: validate::Format) -> Result<()> {
+    let mut cards = read_deck(&deck)?;
+    let outdated: Vec<Card> = cards
+        .iter()
+        .filter(|c| c.legalities.is_empty())
+        .cloned()
+        .collect();
+    if !outdated.is_empty() {
+        let fresh = look_up_printings(&outdated, false)?;
+        for card in cards.iter_mut().filter(|c| c.legalities.is_empty()) {
+            let current = fresh
+                .iter()
+                .find(|f| f.set == card.set && f.collector_number == card.collector_number);
+            if let Some(current) = current {
+                card.legalities = current.legalities.clone();
+                card.type_line = current.type_line.clone();
+                card.color_identity = current.color_identity.clone();
+            }
+        }
+    }
+
+    let violations = validate::validate(&cards, format);
+    if violations.is_empty() {
+        println!("{deck} is legal in {}.", format.name());
+        return Ok(());
+    }
+    for violation in &violations {
+        println!("{violation}");
+    }
+    Err(anyhow!(
+        "{deck} is not legal in {}, found {} problems.",
+        format.name(),
+        violations.len()
+    ))
+}
 
-    archive.iter().for_each(|card| {
-        let line = format!(
-            "{} {} ({}) {} {}\n",
-            card.count,
-            card.name,
-            card.set.to_ascii_uppercase(),
-            card.collector_number,
-            if card.foil { "*F*" } else { "" }
+/// Reads the cards of `deck`, which is either the name of a list, the path of
+/// an archive, or the path of a deck list or Moxfield CSV to look up like
+/// `import` does.
+fn read_deck(deck: &str) -> Result<Vec<Card>> {
+    let path = Path::new(deck);
+    if !path.is_file() {
+        return Ok(read_collection(Some(existing_list_path(deck)?))?.0);
+    }
+    let format = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => return Ok(read_collection(Some(path.to_path_buf()))?.0),
+        Some("csv") => ImportType::Csv,
+        _ => ImportType::Deck,
+    };
+
+    let content =
+        fs::read_to_string(path).map_err(|e| anyhow!("Could not read {}: {e}", path.display()))?;
+    let (lines, mut unresolved) = match format {
+        ImportType::Deck => import::parse_deck_list(&content),
+        ImportType::Csv => import::parse_moxfield_csv(&content)?,
+    };
+    let client = scryfall_client()?;
+    let db = CardDb::open(card_db_path()?);
+    let (cards, unresolved_lookups) = import::resolve(lines, &client, &db);
+    unresolved.extend(unresolved_lookups);
+    if !unresolved.is_empty() {
+        eprint!(
+            "{} lines could not be looked up and are not checked:\n{}",
+            unresolved.len(),
+            import::format_report(&unresolved)
         );
-        output.push_str(&line);
-    });
+    }
+    Ok(cards)
+}
 
-    output
+/// Looks up the current Scryfall data of the printings of `cards`, from the
+/// local card database or, for printings not in it (or with `fetch`), from
+/// Scryfall. Printings neither knows are left out.
+fn look_up_printings(cards: &[Card], fetch: bool) -> Result<Vec<Card>> {
+    let mut printings: Vec<(String, String)> = cards
+        .iter()
+        .map(|c| (c.set.clone(), c.collector_number.clone()))
+        .collect();
+    printings.sort();
+    printings.dedup();
+    let db = CardDb::open(card_db_path()?);
+    let mut fresh = Vec::new();
+    let mut missing = Vec::new();
+    for (set, number) in printings {
+        match fetch {
+            true => missing.push((set, number)),
+            false => match db.get(&set, &number)? {
+                Some(card) => fresh.push(card),
+                None => missing.push((set, number)),
+            },
+        }
+    }
+    if !missing.is_empty() {
+        fresh.extend(scryfall::query_collection(&missing, &scryfall_client()?)?);
+    }
+    Ok(fresh)
 }
 
-fn command_list_create(name: String, _set_used: bool) -> Result<()> {
-    let root = archive_path();
-    let root = root.join(format!("{name}.json"));
-    let Archive(empty_archive) = Archive::default();
+/// Re-prices every card in the archive at `path` from the local card database
+/// or, for cards not in it (or with `fetch`), from Scryfall. Prints the value
+/// of the archive, and stores it as a snapshot next to the archive, ie
+/// `.collection.json.prices.json`, to compare against next time.
+fn command_value(path: Option<PathBuf>, top: usize, fetch: bool) -> Result<()> {
+    let path = match path {
+        Some(path) => path,
+        None => default_collection_path()?,
+    };
+    let Archive(cards) = read_collection(Some(path.clone()))?;
+    let fresh = look_up_printings(&cards, fetch)?;
+
+    let cards = modify_archive(Some(path.clone()), |a| {
+        value::reprice(a, &fresh);
+        Ok(a.clone())
+    })?;
+    let now = chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false);
+    let valuation = value::value(&cards, top, Config::load()?.preferred_currency(), now);
+
+    let snapshots_path = sibling_path(&path, ".", ".prices.json");
+    let mut snapshots: Vec<PriceSnapshot> = match fs::read_to_string(&snapshots_path) {
+        Ok(content) => serde_json::from_str(&content)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(anyhow!("Could not read price snapshots: {e}")),
+    };
+    print!("{}", value::format_report(&valuation, snapshots.last()));
+    snapshots.push(valuation.snapshot);
+    write_atomically(&snapshots_path, serde_json::to_string_pretty(&snapshots)?)
+}
 
-    let file_content = serde_json::to_string_pretty(&empty_archive)?;
-    let _ = std::fs::write(root.clone(), file_content);
+/// Prints which printings of `set` the collection has fewer than one copy, or
+/// with `playset` four copies, of. The set's cards come from the local card
+/// database or, if the set isn't in it, from Scryfall.
+fn command_set_progress(
+    set: String,
+    playset: bool,
+    collection: Option<PathBuf>,
+    want_list: Option<PathBuf>,
+) -> Result<()> {
+    let collection = match collection {
+        Some(path) => path,
+        None => archive_collection_path()?,
+    };
+    let Archive(collection) = read_collection(Some(collection))?;
+    let db = CardDb::open(card_db_path()?);
+    let set_cards = scryfall::query_set_cards(&set, &scryfall_client()?, &db)?;
+    if set_cards.is_empty() {
+        return Err(anyhow!("Set {set} has no cards."));
+    }
+
+    let target = match playset {
+        true => progress::PLAYSET,
+        false => 1,
+    };
+    let progress = progress::progress(&set, &set_cards, &collection, target);
+    print!("{}", progress::format_report(&progress));
 
-    println!("Created new list at {}", root.display());
+    if let Some(path) = want_list {
+        fs::write(&path, DeckList.export(&progress.missing))
+            .map_err(|e| anyhow!("Could not write want-list to {}: {e}", path.display()))?;
+        println!("Wrote want-list to {}", path.display());
+    }
     Ok(())
 }
 
-fn command_list_use(name: Option<String>) -> Result<()> {
-    let mut state = read_state()?;
+/// Prints statistics of the archive at `path`, or shows them as charts. Set
+/// sizes come from the local card database or, for sets not in it, from
+/// Scryfall. If Scryfall can't be reached, those sets are reported without a
+/// size.
+fn command_stats(path: Option<PathBuf>, chart: bool) -> Result<()> {
+    let Archive(cards) = read_collection(path)?;
+    let currency = Config::load()?.preferred_currency();
 
-    match name {
-        Some(specified_name) => {
-            // TODO(sar): Check for ENOENT here
-            let old_deck = state.currently_used_deck;
-            state.currently_used_deck = Some(specified_name.clone());
-            match old_deck {
-                Some(old_deck) => println!("Changed used deck from {old_deck} to {specified_name}"),
-                None => println!("Changed used deck from the collection to {specified_name}"),
+    let mut sets: Vec<&str> = cards.iter().map(|c| c.set.as_str()).collect();
+    sets.sort();
+    sets.dedup();
+    let db = CardDb::open(card_db_path()?);
+    let mut set_sizes = std::collections::BTreeMap::new();
+    let mut missing = Vec::new();
+    for set in sets {
+        match db.set_size(set)? {
+            Some(size) => {
+                set_sizes.insert(set.to_string(), size);
             }
+            None => missing.push(set),
         }
-        None => {
-            state.currently_used_deck = None;
-            println!("Unset current deck, defaulting back to the collection.");
+    }
+    if !missing.is_empty() {
+        match scryfall_client().and_then(|client| scryfall::query_sets(&client)) {
+            Ok(infos) => set_sizes.extend(
+                infos
+                    .into_iter()
+                    .filter(|info| missing.contains(&info.code.as_str()))
+                    .map(|info| (info.code, info.card_count)),
+            ),
+            Err(e) => eprintln!("Could not look up the size of {}: {e}", missing.join(", ")),
         }
     }
 
-    // if absolute path, use that
-    // if bare string, use format!("{config_dir()}.{name}.json)`
-    // if empty/None, open `skim`
-    // if not found, offer to create
+    let stats = stats::stats(&cards, currency, &set_sizes);
+    match chart {
+        true => stats::chart(&stats),
+        false => {
+            print!("{}", stats::format_report(&stats));
+            Ok(())
+        }
+    }
+}
 
-    write_state(state)
+/// Allocates, frees and moves copies of the collection between decks. Every
+/// change is a single edit of the collection, so it either happens as a whole
+/// or not at all.
+fn command_alloc(subcommand: AllocCommands) -> Result<()> {
+    let collection = archive_collection_path()?;
+    let parse = |cards: Vec<String>| -> Result<Vec<import::ImportLine>> {
+        cards.iter().map(|c| import::parse_deck_line(c)).collect()
+    };
+    let deck_name = |name: &str| -> Result<()> {
+        match name == COLLECTION_NAME {
+            true => Err(anyhow!("Cards can't be allocated to the collection itself.")),
+            false => list_path(name).map(|_| ()),
+        }
+    };
+
+    match subcommand {
+        AllocCommands::Add { deck, cards } if cards.is_empty() => {
+            deck_name(&deck)?;
+            let Archive(cards) = read_collection(Some(existing_list_path(&deck)?))?;
+            let availability =
+                modify_archive(Some(collection), |c| Ok(alloc::allocate_deck(c, &deck, &cards)))?;
+            let allocated: u32 = availability.used.iter().map(|(_, n)| n).sum();
+            println!("Allocated {allocated} cards to {deck}.");
+            if !availability.missing.is_empty() {
+                print!(
+                    "These are missing, see `check`:\n{}",
+                    DeckList.export(&availability.missing)
+                );
+            }
+        }
+        AllocCommands::Add { deck, cards } => {
+            deck_name(&deck)?;
+            let lines = parse(cards)?;
+            let moved = modify_archive(Some(collection), |c| {
+                alloc::transfer(c, None, Some(&deck), &lines)
+            })?;
+            println!("Allocated {moved} cards to {deck}.");
+        }
+        AllocCommands::Remove { deck, cards } => {
+            let lines = parse(cards)?;
+            let moved =
+                modify_archive(Some(collection), |c| alloc::transfer(c, Some(&deck), None, &lines))?;
+            println!("Freed {moved} cards from {deck}.");
+        }
+        AllocCommands::Move { from, to, cards } => {
+            deck_name(&to)?;
+            let lines = parse(cards)?;
+            let moved = modify_archive(Some(collection), |c| {
+                alloc::transfer(c, Some(&from), Some(&to), &lines)
+            })?;
+            println!("Moved {moved} cards from {from} to {to}.");
+        }
+        AllocCommands::Ls { deck: None } => {
+            let Archive(cards) = read_collection(Some(collection))?;
+            print!("{}", alloc::format_summary(&cards));
+        }
+        AllocCommands::Ls { deck: Some(deck) } => {
+            let Archive(cards) = read_collection(Some(collection))?;
+            print!("{}", DeckList.export(&alloc::deck_copies(&cards, &deck)));
+        }
+    }
+    Ok(())
 }
 
-/// Adds `c` to the archive specified at `path`, if not, the deck specified in
-/// the state, if not that, the default collection. Returns either the amount of
-/// cards now present in the collection, or an error.
-fn edit_archive(c: Card, path: Option<PathBuf>, removal: bool) -> Result<u32> {
-    let Archive(mut a) = read_collection(path.clone())?;
-    let card_in_archive = a.iter_mut().find(|archive_card| {
-        archive_card.set == c.set
-            && archive_card.collector_number == c.collector_number
-            && archive_card.foil == c.foil
-    });
-
-    let count = match card_in_archive {
-        Some(archive_card) => {
-            if removal {
-                archive_card.count -= 1;
-            } else {
-                archive_card.count += 1;
+/// Moves the collection's allocations to deck `from` over to `to`, or frees
+/// them for `None`. Used when lists are renamed or deleted.
+fn reassign_allocations(from: &str, to: Option<&str>) -> Result<u32> {
+    let collection = archive_collection_path()?;
+    let Archive(cards) = read_collection(Some(collection.clone()))?;
+    if cards.iter().all(|c| c.allocated_to(from) == 0) {
+        return Ok(0);
+    }
+    modify_archive(Some(collection), |c| alloc::transfer(c, Some(from), to, &[]))
+}
+
+fn command_config(subcommand: ConfigCommands) -> Result<()> {
+    let mut config = Config::load()?;
+    match subcommand {
+        ConfigCommands::Get { key } => match config.get(&key)? {
+            Some(value) => println!("{value}"),
+            None => println!("{key} is not set."),
+        },
+        ConfigCommands::Set { key, value } => {
+            config.set(&key, Some(&value))?;
+            config.save()?;
+        }
+        ConfigCommands::Unset { key } => {
+            config.set(&key, None)?;
+            config.save()?;
+        }
+        ConfigCommands::List => {
+            println!("# {}", config::config_file_path()?.display());
+            for key in config::KEYS {
+                match config.get(key)? {
+                    Some(value) => println!("{key} = {value}"),
+                    None => println!("{key} (unset)"),
+                }
+            }
+            println!("# Data is stored in {}", config.data_dir()?.display());
+        }
+    }
+    Ok(())
+}
+
+/// Builds the local card database from a Scryfall bulk data file. Once this
+/// exists, `add` only asks Scryfall for cards that aren't in it.
+fn command_db_import(file: PathBuf) -> Result<()> {
+    let bulk_file = match fs::File::open(&file) {
+        Ok(f) => f,
+        Err(e) => return Err(anyhow!("Could not open {}: {e}", file.display())),
+    };
+    let stats = CardDb::open(card_db_path()?).import(bulk_file)?;
+
+    println!(
+        "Imported {} cards from {} sets into {}",
+        stats.cards,
+        stats.sets,
+        card_db_path()?.display()
+    );
+    if stats.skipped > 0 {
+        println!("Skipped {} entries that could not be read.", stats.skipped);
+    }
+    Ok(())
+}
+
+/// Searches the collection at `path` for cards matching `query`, and prints
+/// them in the same format as `export` does by default. See `search::Query`
+/// for the supported syntax.
+fn command_search(path: Option<PathBuf>, query: Vec<String>) -> Result<()> {
+    let query = search::parse_query(&query.join(" "))?;
+    let Archive(a) = read_collection(path)?;
+    let matches: Vec<Card> = a.into_iter().filter(|card| query.matches(card)).collect();
+
+    print!("{}", DeckList.export(&matches));
+    Ok(())
+}
+
+/// Creates the list `name`, empty or with the cards of the list `from` or the
+/// deck list file `template`. Existing lists are only replaced with `force`,
+/// and even then the old version is kept as a backup.
+fn command_list_create(
+    name: String,
+    set_used: bool,
+    force: bool,
+    from: Option<String>,
+    template: Option<PathBuf>,
+) -> Result<()> {
+    let path = match force {
+        true => list_path(&name)?,
+        false => new_list_path(&name)?,
+    };
+
+    let cards = match (from, template) {
+        (Some(from), _) => read_list_copy(&existing_list_path(&from)?)?,
+        (None, Some(template)) => {
+            let content = fs::read_to_string(&template)
+                .map_err(|e| anyhow!("Could not read {}: {e}", template.display()))?;
+            let (lines, mut unresolved) = import::parse_deck_list(&content);
+            let client = scryfall_client()?;
+            let (cards, unresolved_lookups) =
+                import::resolve(lines, &client, &CardDb::open(card_db_path()?));
+            unresolved.extend(unresolved_lookups);
+            for line in &unresolved {
+                eprintln!("Skipped `{}`: {}", line.raw, line.reason);
+            }
+            let mut merged = Vec::new();
+            match is_collection(Some(&path))? {
+                true => import::merge_into_collection(&mut merged, cards),
+                false => import::merge(&mut merged, cards),
             }
-            archive_card.count
+            merged
         }
+        (None, None) => Vec::new(),
+    };
+
+    let _lock = lock_archive(&path)?;
+    write_collection(serde_json::to_string_pretty(&cards)?, Some(path.clone()))
+        .map_err(|e| anyhow!("Could not create {}: {e}", path.display()))?;
+    println!("Created new list at {}", path.display());
+
+    if set_used {
+        let mut state = read_state()?;
+        state.currently_used_deck = (name != COLLECTION_NAME).then(|| name.clone());
+        write_state(state)?;
+        println!("{name} is now the current list.");
+    }
+    Ok(())
+}
+
+/// Makes `name` the current list, which `add`, `export` and the like work on
+/// when not given a path. `name` is either one of the lists next to the
+/// collection, or an absolute path to a list anywhere else. Without a name, a
+/// picker over all lists opens. Lists that don't exist yet can be created.
+fn command_list_use(name: Option<String>) -> Result<()> {
+    let name = match name {
+        Some(name) => name,
         None => {
-            if removal {
-                return Err(anyhow!(
-                    "Can't remove card from collection, because no copies are in the collection."
-                ));
+            let names = list_names()?;
+            match picker::pick("Use which list?", &names)? {
+                Some(i) => names[i].clone(),
+                None => return Ok(()),
             }
-            a.push(c.clone());
-            c.count
         }
     };
+
+    let external = Path::new(&name).is_absolute();
+    let path = match external {
+        true => PathBuf::from(&name),
+        false => list_path(&name)?,
+    };
+    if !path.exists() {
+        if !confirm(
+            &format!("There is no list at {}, create it?", path.display()),
+            read_stdin_line,
+        )? {
+            return Ok(());
+        }
+        match external {
+            true => write_atomically(&path, "[]".to_string())?,
+            false => command_list_create(name.clone(), false, false, None, None)?,
+        }
+    }
+
+    let mut state = read_state()?;
+    let new_deck = match name == COLLECTION_NAME {
+        true => None,
+        false => Some(name),
+    };
+    let old_deck = std::mem::replace(&mut state.currently_used_deck, new_deck.clone());
+    match (old_deck, new_deck) {
+        (Some(old_deck), Some(new_deck)) => {
+            println!("Changed used deck from {old_deck} to {new_deck}")
+        }
+        (None, Some(new_deck)) => println!("Changed used deck from the collection to {new_deck}"),
+        (_, None) => println!("Unset current deck, defaulting back to the collection."),
+    }
+    write_state(state)
+}
+
+/// Prints every list in the archive directory, its card count and the value
+/// of its cards as of when they were last priced.
+fn command_list_ls() -> Result<()> {
+    let current = default_collection_path()?;
+    let mut lists = Vec::new();
+    for name in list_names()? {
+        let path = list_path(&name)?;
+        lists.push((name, path));
+    }
+    // A current list outside of the archive directory is shown by its path.
+    if !lists.iter().any(|(_, path)| *path == current) {
+        lists.push((current.display().to_string(), current.clone()));
+    }
+
+    for (name, path) in lists {
+        let Archive(cards) = read_collection(Some(path.clone()))?;
+        let copies: u32 = cards.iter().map(|c| c.count).sum();
+        let PriceSnapshot { usd, eur, .. } =
+            value::value(&cards, 0, Currency::default(), String::new()).snapshot;
+        println!(
+            "{} {name:<30}{copies:>7} cards{:>12}{:>12}",
+            if path == current { "*" } else { " " },
+            format!("${:.2}", usd.total().round_dp(2)),
+            format!("{:.2}€", eur.total().round_dp(2)),
+        );
+    }
+    Ok(())
+}
+
+/// Prints the list called `name`, or the current list, as a deck list.
+fn command_list_show(name: Option<String>) -> Result<()> {
+    let path = match name {
+        Some(name) => existing_list_path(&name)?,
+        None => default_collection_path()?,
+    };
+    let Archive(cards) = read_collection(Some(path))?;
+    print!("{}", DeckList.export(&cards));
+    Ok(())
+}
+
+fn command_list_delete(name: String, yes: bool) -> Result<()> {
+    if name == COLLECTION_NAME {
+        return Err(anyhow!("The collection itself can't be deleted."));
+    }
+    let path = existing_list_path(&name)?;
+    let Archive(cards) = read_collection(Some(path.clone()))?;
+    let copies: u32 = cards.iter().map(|c| c.count).sum();
+    if !yes
+        && !confirm(
+            &format!("Delete {name} and its {copies} cards?"),
+            read_stdin_line,
+        )?
+    {
+        return Ok(());
+    }
+
+    let _lock = lock_archive(&path)?;
+    fs::remove_file(&path).map_err(|e| anyhow!("Could not delete {}: {e}", path.display()))?;
+    println!("Deleted {name}. Its backups are kept next to it, should you need them.");
+    let freed = reassign_allocations(&name, None)?;
+    if freed > 0 {
+        println!("Freed the {freed} cards allocated to it.");
+    }
+
+    let mut state = read_state()?;
+    if state.currently_used_deck.as_deref() == Some(name.as_str()) {
+        state.currently_used_deck = None;
+        write_state(state)?;
+        println!("It was the current list, defaulting back to the collection.");
+    }
+    Ok(())
+}
+
+fn command_list_rename(from: String, to: String) -> Result<()> {
+    if from == COLLECTION_NAME {
+        return Err(anyhow!("The collection itself can't be renamed."));
+    }
+    let from_path = existing_list_path(&from)?;
+    let to_path = new_list_path(&to)?;
+
+    move_list(&from_path, &to_path)
+        .map_err(|e| anyhow!("Could not rename {from} to {to}: {e}"))?;
+    println!("Renamed {from} to {to}.");
+    reassign_allocations(&from, Some(&to))?;
+
+    let mut state = read_state()?;
+    if state.currently_used_deck.as_deref() == Some(from.as_str()) {
+        state.currently_used_deck = Some(to);
+        write_state(state)?;
+    }
+    Ok(())
+}
+
+/// Moves the archive at `from` to `to`, along with its price snapshots and
+/// backups, so that they stay with the list. The lock file is removed once the
+/// move is done, nothing can be waiting on a list under the old name anymore.
+fn move_list(from: &Path, to: &Path) -> Result<()> {
+    let lock = lock_archive(from)?;
+    fs::rename(from, to)?;
+    let snapshots = sibling_path(from, ".", ".prices.json");
+    if snapshots.exists() {
+        fs::rename(snapshots, sibling_path(to, ".", ".prices.json"))?;
+    }
+
+    let prefix = sibling_path(from, ".", "");
+    let prefix = prefix.file_name().unwrap().to_string_lossy().to_string();
+    for backup in archive_backups(from)? {
+        let name = backup.file_name().unwrap().to_string_lossy().to_string();
+        let suffix = name.strip_prefix(&prefix).unwrap_or_default();
+        fs::rename(&backup, sibling_path(to, ".", suffix))?;
+    }
+
+    drop(lock);
+    fs::remove_file(sibling_path(from, ".", ".lock"))?;
+    Ok(())
+}
+
+fn command_list_copy(from: String, to: String) -> Result<()> {
+    let from_path = existing_list_path(&from)?;
+    let to_path = new_list_path(&to)?;
+
+    let cards = read_list_copy(&from_path)?;
+    write_atomically(&to_path, serde_json::to_string_pretty(&cards)?)?;
+    println!("Copied {from} to {to}.");
+    Ok(())
+}
+
+/// Reads the cards of the list at `path` to start another list with.
+/// Allocations only mean something in the collection, so they're left out.
+fn read_list_copy(path: &Path) -> Result<Vec<Card>> {
+    let Archive(cards) = read_collection(Some(path.to_path_buf()))?;
+    Ok(cards
+        .into_iter()
+        .map(|card| Card {
+            allocations: Default::default(),
+            ..card
+        })
+        .collect())
+}
+
+/// Shows `prompt` and reads a line from stdin, for `confirm`.
+fn read_stdin_line(prompt: &str) -> Result<String> {
+    print!("{prompt}");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(answer)
+}
+
+/// The name the collection goes by among the lists.
+const COLLECTION_NAME: &str = "collection";
+
+/// The names of all lists in the archive directory, the collection included.
+fn list_names() -> Result<Vec<String>> {
+    let config_file = config::config_file_path()?;
+    let mut names = Vec::new();
+    for entry in fs::read_dir(archive_path()?)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+            continue;
+        };
+        if let Some(name) = name.strip_suffix(".json")
+            && !name.starts_with('.')
+            && !name.starts_with('_')
+            && path.is_file()
+            && path != config_file
+        {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// The path of the list called `name`, which doesn't have to exist.
+fn list_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.starts_with(['.', '_']) || name.contains(['/', '\\']) {
+        return Err(anyhow!(
+            "`{name}` is not a valid list name, it can't contain slashes or start with `.` or `_`."
+        ));
+    }
+    let path = archive_path()?.join(format!("{name}.json"));
+    // The config file lives in the same folder, unless configured otherwise.
+    if path == config::config_file_path()? {
+        return Err(anyhow!("`{name}` is reserved for the config file."));
+    }
+    Ok(path)
+}
+
+/// Like `list_path`, but fails if the list doesn't exist.
+fn existing_list_path(name: &str) -> Result<PathBuf> {
+    let path = list_path(name)?;
+    if !path.exists() {
+        return Err(anyhow!("There is no list called {name}, see `list ls`."));
+    }
+    Ok(path)
+}
+
+/// Like `list_path`, but fails if the list already exists.
+fn new_list_path(name: &str) -> Result<PathBuf> {
+    let path = list_path(name)?;
+    if path.exists() {
+        return Err(anyhow!("A list called {name} already exists."));
+    }
+    Ok(path)
+}
+
+/// Adds `c` to the archive specified at `path`, if not, the deck specified in
+/// the state, if not that, the default collection. `c.count` is the number of
+/// copies to add or remove. Returns either the amount of cards now present in
+/// the collection, or an error.
+fn edit_archive(c: Card, path: Option<PathBuf>, removal: bool) -> Result<u32> {
+    modify_archive(path, |a| {
+        let card_in_archive = a
+            .iter_mut()
+            .find(|archive_card| archive_card.is_same_printing(&c));
+
+        let count = match card_in_archive {
+            Some(archive_card) => {
+                if removal {
+                    if archive_card.count < c.count {
+                        return Err(anyhow!(
+                            "Can't remove {} copies from collection, only {} are in the collection.",
+                            c.count,
+                            archive_card.count
+                        ));
+                    }
+                    archive_card.count -= c.count;
+                    archive_card.clamp_allocations();
+                } else {
+                    archive_card.count += c.count;
+                }
+                archive_card.count
+            }
+            None => {
+                if removal {
+                    return Err(anyhow!(
+                        "Can't remove card from collection, because no copies are in the collection."
+                    ));
+                }
+                a.push(c.clone());
+                c.count
+            }
+        };
+        Ok(count)
+    })
+}
+
+/// Reads the archive at `path` (or the default collection, see
+/// `read_collection`), hands the cards to `edit`, and writes them back if
+/// `edit` succeeded. This is the one place archives get modified in. The
+/// archive stays locked for the whole read-modify-write, so that concurrent
+/// sessions can't overwrite each other's changes.
+fn modify_archive<T>(
+    path: Option<PathBuf>,
+    edit: impl FnOnce(&mut Vec<Card>) -> Result<T>,
+) -> Result<T> {
+    let path = match path {
+        Some(path) => path,
+        None => default_collection_path()?,
+    };
+    let _lock = lock_archive(&path)?;
+
+    let Archive(mut a) = read_collection(Some(path.clone()))?;
+    let res = edit(&mut a)?;
     let file_content = serde_json::to_string_pretty(&a)?;
-    write_collection(file_content, path)?;
-    Ok(count)
+    write_collection(file_content, Some(path))?;
+    Ok(res)
+}
+
+/// Takes an exclusive advisory lock on the archive at `path`, which is held
+/// until the returned file is dropped. The lock lives on a separate file, as
+/// the archive itself gets replaced on every write.
+fn lock_archive(path: &Path) -> Result<fs::File> {
+    let lock_path = sibling_path(path, ".", ".lock");
+    let lock_file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|e| anyhow!("Could not open lock file {}: {e}", lock_path.display()))?;
+
+    match lock_file.try_lock() {
+        Ok(()) => {}
+        Err(fs::TryLockError::WouldBlock) => {
+            eprintln!(
+                "{} is in use by another crackathon, waiting for it...",
+                path.display()
+            );
+            lock_file.lock()?;
+        }
+        Err(fs::TryLockError::Error(e)) => return Err(anyhow!("Could not lock archive: {e}")),
+    }
+    Ok(lock_file)
 }
 
 fn read_collection(explicit_path: Option<PathBuf>) -> Result<Archive> {
@@ -384,8 +1580,8 @@ fn read_collection(explicit_path: Option<PathBuf>) -> Result<Archive> {
             }
         }
     };
-    let res: Result<Archive> = match serde_json::from_str(&file) {
-        Ok(res) => return Ok(res),
+    let mut archive = match serde_json::from_str::<Archive>(&file) {
+        Ok(res) => res,
         _ => {
             let fallback_res: OldArchive = match serde_json::from_str(&file) {
                 Ok(res) => res,
@@ -401,10 +1597,11 @@ fn read_collection(explicit_path: Option<PathBuf>) -> Result<Archive> {
                 .reduce(|acc, el| acc.into_iter().chain(el).collect())
                 .unwrap();
 
-            Ok(Archive(combined_list))
+            Archive(combined_list)
         }
     };
-    res
+    archive.0.iter_mut().for_each(Card::migrate);
+    Ok(archive)
 }
 
 fn write_collection(content: String, explicit_path: Option<PathBuf>) -> Result<()> {
@@ -413,21 +1610,88 @@ fn write_collection(content: String, explicit_path: Option<PathBuf>) -> Result<(
         None => default_collection_path()?,
     };
 
-    std::fs::write(path, content)?;
+    if path.exists() {
+        backup_archive(&path)?;
+    }
+    write_atomically(&path, content)
+}
+
+/// How many backups are kept next to each archive, see `backup_archive`.
+const BACKUP_COUNT: usize = 10;
+
+/// Copies the archive at `path` to a timestamped, hidden backup next to it,
+/// ie `.collection.json.20250101T120000123456789.bak`, and deletes all but the
+/// newest `BACKUP_COUNT` backups.
+fn backup_archive(path: &Path) -> Result<()> {
+    let timestamp = chrono::Local::now().format("%Y%m%dT%H%M%S%9f");
+    let backup_path = sibling_path(path, ".", &format!(".{timestamp}.bak"));
+    fs::copy(path, &backup_path).map_err(|e| {
+        anyhow!(
+            "Could not back up archive to {}: {e}",
+            backup_path.display()
+        )
+    })?;
+
+    let mut backups = archive_backups(path)?;
+    backups.sort();
+    let excess = backups.len().saturating_sub(BACKUP_COUNT);
+    for old_backup in &backups[..excess] {
+        fs::remove_file(old_backup)?;
+    }
     Ok(())
 }
 
+/// All backups of the archive at `path`, in no particular order.
+fn archive_backups(path: &Path) -> Result<Vec<PathBuf>> {
+    let prefix = sibling_path(path, ".", ".");
+    let prefix = prefix.file_name().unwrap().to_string_lossy().to_string();
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry_path = entry?.path();
+        let name = entry_path.file_name().unwrap().to_string_lossy();
+        if name.starts_with(&prefix) && name.ends_with(".bak") {
+            backups.push(entry_path);
+        }
+    }
+    Ok(backups)
+}
+
+/// Writes `content` to a temporary file next to `path`, and renames it over
+/// `path` once it's fully on disk. A crash halfway through leaves the old
+/// file intact.
+fn write_atomically(path: &Path, content: String) -> Result<()> {
+    let temp_path = sibling_path(path, ".", ".tmp");
+    let mut temp_file = fs::File::create(&temp_path)?;
+    temp_file.write_all(content.as_bytes())?;
+    temp_file.sync_all()?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// `path` with `prefix` and `suffix` added to its file name, ie
+/// `dir/.collection.json.lock` for `dir/collection.json`.
+fn sibling_path(path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{prefix}{file_name}{suffix}"))
+}
+
 fn default_collection_path() -> Result<PathBuf> {
     let state = read_state()?;
     let res = match state.currently_used_deck {
-        Some(deck_name) => archive_path().join(format!("{deck_name}.json")),
-        None => archive_collection_path(),
+        Some(deck_name) if Path::new(&deck_name).is_absolute() => PathBuf::from(deck_name),
+        Some(deck_name) => archive_path()?.join(format!("{deck_name}.json")),
+        None => archive_collection_path()?,
     };
     Ok(res)
 }
 
 fn read_state() -> Result<State> {
-    let file = match std::fs::read_to_string(state_file_path()) {
+    let file = match std::fs::read_to_string(state_file_path()?) {
         Ok(res) => res,
         Err(e) => {
             match e.kind() {
@@ -448,23 +1712,197 @@ fn read_state() -> Result<State> {
 
 fn write_state(s: State) -> Result<()> {
     let file_content = serde_json::to_string_pretty(&s)?;
-    std::fs::write(state_file_path(), file_content)?;
-    Ok(())
+    write_locked(&state_file_path()?, file_content)
+}
+
+/// Like `write_atomically`, but holds the lock on `path` so that two
+/// crackathons writing it at once don't trip over the shared temp file.
+fn write_locked(path: &Path, content: String) -> Result<()> {
+    let _lock = lock_archive(path)?;
+    write_atomically(path, content)
 }
 
-fn archive_path() -> PathBuf {
-    let homedir = env::home_dir().expect("Can't get user home directory");
-    let config_folder = homedir.join(".config").join("crack");
-    match fs::create_dir_all(&config_folder) {
-        Ok(_) => config_folder,
-        Err(e) => panic!("can't create folder at {}: {}", config_folder.display(), e),
+/// The data directory, see `Config::data_dir`. Created if it doesn't exist.
+fn archive_path() -> Result<PathBuf> {
+    let data_dir = Config::load()?.data_dir()?;
+    fs::create_dir_all(&data_dir)
+        .map_err(|e| anyhow!("Can't create folder at {}: {e}", data_dir.display()))?;
+    Ok(data_dir)
+}
+
+/// Whether `path`, or the current list for `None`, is the `collection` list.
+fn is_collection(path: Option<&Path>) -> Result<bool> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => default_collection_path()?,
+    };
+    let collection = archive_collection_path()?;
+    if path == collection {
+        return Ok(true);
     }
+    Ok(matches!(
+        (fs::canonicalize(&path), fs::canonicalize(&collection)),
+        (Ok(a), Ok(b)) if a == b
+    ))
+}
+
+fn archive_collection_path() -> Result<PathBuf> {
+    Ok(archive_path()?.join("collection.json"))
 }
 
-fn archive_collection_path() -> PathBuf {
-    archive_path().join("collection.json")
+fn state_file_path() -> Result<PathBuf> {
+    Ok(archive_path()?.join("_state.json"))
 }
 
-fn state_file_path() -> PathBuf {
-    archive_path().join("_state.json")
+fn card_db_path() -> Result<PathBuf> {
+    Ok(archive_path()?.join("db"))
+}
+
+/// A Scryfall client for the API root from the config.
+fn scryfall_client() -> Result<scryfall::Client> {
+    scryfall::Client::new(Config::load()?.scryfall_base_url())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::fixture_card;
+
+    fn temp_archive(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("crackathon-test-{}-{name}", std::process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("collection.json")
+    }
+
+    #[test]
+    fn test_concurrent_edits_are_not_lost() {
+        let path = temp_archive("concurrent");
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        edit_archive(fixture_card("1"), Some(path.clone()), false).unwrap();
+                    }
+                })
+            })
+            .collect();
+        threads.into_iter().for_each(|t| t.join().unwrap());
+
+        let Archive(cards) = read_collection(Some(path)).unwrap();
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].count, 40);
+    }
+
+    #[test]
+    fn test_concurrent_writes_do_not_fail() {
+        let path = temp_archive("concurrent-writes");
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        write_locked(&path, format!("{{\"thread\": {i}}}")).unwrap();
+                    }
+                })
+            })
+            .collect();
+        threads.into_iter().for_each(|t| t.join().unwrap());
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(&content).is_ok());
+    }
+
+    #[test]
+    fn test_backups_are_rotated() {
+        let path = temp_archive("backups");
+        for _ in 0..BACKUP_COUNT + 5 {
+            edit_archive(fixture_card("1"), Some(path.clone()), false).unwrap();
+        }
+
+        assert_eq!(archive_backups(&path).unwrap().len(), BACKUP_COUNT);
+        assert!(!sibling_path(&path, ".", ".tmp").exists());
+        let Archive(cards) = read_collection(Some(path)).unwrap();
+        assert_eq!(cards[0].count, BACKUP_COUNT as u32 + 5);
+    }
+
+    #[test]
+    fn test_move_list_takes_its_backups_along() {
+        let from = temp_archive("move-list");
+        for _ in 0..3 {
+            edit_archive(fixture_card("1"), Some(from.clone()), false).unwrap();
+        }
+        let to = from.with_file_name("burn.json");
+
+        move_list(&from, &to).unwrap();
+        assert!(!from.exists());
+        assert!(!sibling_path(&from, ".", ".lock").exists());
+        assert!(archive_backups(&from).unwrap().is_empty());
+        assert_eq!(archive_backups(&to).unwrap().len(), 2);
+        let Archive(cards) = read_collection(Some(to)).unwrap();
+        assert_eq!(cards[0].count, 3);
+    }
+
+    #[test]
+    fn test_copies_leave_allocations_behind() {
+        let path = temp_archive("copy-allocations");
+        let mut bolts = fixture_card("1");
+        bolts.allocations.insert("burn".to_string(), 1);
+        edit_archive(bolts, Some(path.clone()), false).unwrap();
+
+        let cards = read_list_copy(&path).unwrap();
+        assert_eq!(cards[0].count, 1);
+        assert!(cards[0].allocations.is_empty());
+    }
+
+    #[test]
+    fn test_confirm() {
+        let answer = |answer: &'static str| move |_: &str| Ok(answer.to_string());
+        assert!(confirm("Sure?", answer("y\n")).unwrap());
+        assert!(confirm("Sure?", answer(" YES ")).unwrap());
+        assert!(!confirm("Sure?", answer("\n")).unwrap());
+        assert!(!confirm("Sure?", answer("nope")).unwrap());
+
+        let mut shown = String::new();
+        confirm("Sure?", |prompt| {
+            shown = prompt.to_string();
+            Ok(String::new())
+        })
+        .unwrap();
+        assert_eq!(shown, "Sure? [y/N]: ");
+    }
+
+    #[test]
+    fn test_collection_has_no_sections() {
+        let mut journal = Journal::default();
+        let mut section = Section::Mainboard;
+        assert!(command_add_meta("sb", &mut journal, &mut section, true, None).is_err());
+        assert_eq!(section, Section::Mainboard);
+        command_add_meta("main", &mut journal, &mut section, true, None).unwrap();
+        command_add_meta("sb", &mut journal, &mut section, false, None).unwrap();
+        assert_eq!(section, Section::Sideboard);
+    }
+
+    #[test]
+    fn test_removal_frees_allocations() {
+        let path = temp_archive("removal-allocations");
+        let mut bolts = Card {
+            count: 4,
+            ..fixture_card("1")
+        };
+        bolts.allocations.insert("burn".to_string(), 4);
+        edit_archive(bolts, Some(path.clone()), false).unwrap();
+
+        let removed = Card {
+            count: 3,
+            ..fixture_card("1")
+        };
+        assert_eq!(edit_archive(removed, Some(path.clone()), true).unwrap(), 1);
+        let Archive(cards) = read_collection(Some(path)).unwrap();
+        assert_eq!(cards[0].allocated_to("burn"), 1);
+    }
 }
@@ -0,0 +1,200 @@
+use std::io::IsTerminal;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListState, Paragraph};
+
+/// Opens a picker over `options` that narrows them down as you type, like
+/// skim or fzf do. Returns the index of the chosen option, or `None` if the
+/// picker was dismissed.
+pub fn pick(title: &str, options: &[String]) -> Result<Option<usize>> {
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!("Can't open a picker, stdin is not a terminal."));
+    }
+    let mut picker = Picker::new(title, options);
+
+    let mut terminal = ratatui::init();
+    let res = picker.run(&mut terminal);
+    ratatui::restore();
+    res
+}
+
+/// How well `query` matches `candidate`, lower is better. `None` if the
+/// characters of `query` don't all appear in `candidate`, in order. Matches
+/// that start early and don't skip much in between score best.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<usize> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    for (i, wanted) in query.to_lowercase().chars().enumerate() {
+        let offset = candidate[position..].iter().position(|&c| c == wanted)?;
+        // Skipping characters before the first match costs less than skipping
+        // them between matches, so `bu` prefers `burn` over `b-u`, and both
+        // over `rakdos burn`.
+        score += match i {
+            0 => offset,
+            _ => offset * 2,
+        };
+        position += offset + 1;
+    }
+    Some(score)
+}
+
+struct Picker<'a> {
+    title: &'a str,
+    options: &'a [String],
+    query: String,
+    /// Indices into `options` that match `query`, best match first.
+    matches: Vec<usize>,
+    list_state: ListState,
+}
+
+impl<'a> Picker<'a> {
+    fn new(title: &'a str, options: &'a [String]) -> Picker<'a> {
+        let mut picker = Picker {
+            title,
+            options,
+            query: String::new(),
+            matches: Vec::new(),
+            list_state: ListState::default(),
+        };
+        picker.refresh();
+        picker
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<Option<usize>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if let Some(result) = self.handle_key(key) {
+                    return Ok(result);
+                }
+            }
+        }
+    }
+
+    /// Returns `Some` once the picker is done, with the chosen option if any.
+    fn handle_key(&mut self, key: KeyEvent) -> Option<Option<usize>> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Some(None),
+            KeyCode::Char('c') if ctrl => return Some(None),
+            KeyCode::Enter => {
+                let selected = self.list_state.selected();
+                return Some(selected.and_then(|i| self.matches.get(i).copied()));
+            }
+            KeyCode::Down | KeyCode::Tab => self.list_state.select_next(),
+            KeyCode::Char('n') if ctrl => self.list_state.select_next(),
+            KeyCode::Up | KeyCode::BackTab => self.list_state.select_previous(),
+            KeyCode::Char('p') if ctrl => self.list_state.select_previous(),
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.refresh();
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.refresh();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn refresh(&mut self) {
+        let mut scored: Vec<(usize, usize)> = self
+            .options
+            .iter()
+            .enumerate()
+            .filter_map(|(i, option)| fuzzy_score(&self.query, option).map(|score| (score, i)))
+            .collect();
+        scored.sort();
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.list_state.select(match self.matches.is_empty() {
+            true => None,
+            false => Some(0),
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [prompt_area, list_area, help_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let prompt =
+            Paragraph::new(format!("> {}", self.query)).block(Block::bordered().title(self.title));
+        frame.render_widget(prompt, prompt_area);
+
+        let items = self.matches.iter().map(|&i| self.options[i].as_str());
+        let list = List::new(items)
+            .block(Block::bordered().title(format!(
+                "{}/{}",
+                self.matches.len(),
+                self.options.len()
+            )))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        frame.render_widget(
+            Line::from("type to filter  ↑/↓ move  enter select  esc cancel").dim(),
+            help_area,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crossterm::event::KeyEventState;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent {
+            code,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("", "burn"), Some(0));
+        assert_eq!(fuzzy_score("BRN", "burn"), Some(2));
+        assert_eq!(fuzzy_score("nb", "burn"), None);
+        assert!(fuzzy_score("bu", "burn") < fuzzy_score("bu", "b-u"));
+        assert!(fuzzy_score("bu", "b-u") < fuzzy_score("bu", "rakdos burn"));
+    }
+
+    #[test]
+    fn test_picker_filters_and_selects() {
+        let options: Vec<String> = ["collection", "rakdos burn", "burn"]
+            .map(String::from)
+            .to_vec();
+        let mut picker = Picker::new("Pick", &options);
+        assert_eq!(picker.matches, vec![0, 1, 2]);
+
+        for c in "burn".chars() {
+            assert_eq!(picker.handle_key(key(KeyCode::Char(c))), None);
+        }
+        assert_eq!(picker.matches, vec![2, 1]);
+        picker.handle_key(key(KeyCode::Down));
+        assert_eq!(picker.handle_key(key(KeyCode::Enter)), Some(Some(1)));
+
+        for c in "xyz".chars() {
+            picker.handle_key(key(KeyCode::Char(c)));
+        }
+        assert_eq!(picker.handle_key(key(KeyCode::Enter)), Some(None));
+        assert_eq!(picker.handle_key(key(KeyCode::Esc)), Some(None));
+    }
+}
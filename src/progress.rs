@@ -0,0 +1,161 @@
+use itertools::Itertools;
+
+use crate::browse::compare_collector_numbers;
+use crate::check;
+use crate::search::Rarity;
+use crate::stats;
+use crate::types::Card;
+
+/// How many copies of every printing make a playset.
+pub const PLAYSET: u32 = 4;
+
+/// How close a collection is to having every printing of a set.
+#[derive(Debug)]
+pub struct Progress {
+    pub set: String,
+    pub name: String,
+    /// How many copies of each printing count as complete.
+    pub target: u32,
+    pub size: usize,
+    /// How many printings the collection has `target` copies of.
+    pub complete: usize,
+    /// The printings the collection has fewer than `target` copies of, each
+    /// with the number of copies still missing, by rarity and collector
+    /// number.
+    pub missing: Vec<Card>,
+}
+
+/// Compares `collection` against `set_cards`, every printing of `set`, by
+/// collector number. Copies of any finish, language and condition count, and
+/// so do copies allocated to decks.
+pub fn progress(set: &str, set_cards: &[Card], collection: &[Card], target: u32) -> Progress {
+    let mut progress = Progress {
+        set: set.to_ascii_lowercase(),
+        name: set_cards
+            .first()
+            .map(|c| c.set_name.clone())
+            .unwrap_or_default(),
+        target,
+        size: set_cards.len(),
+        complete: 0,
+        missing: Vec::new(),
+    };
+    for printing in set_cards {
+        let owned: u32 = collection
+            .iter()
+            .filter(|c| {
+                c.set.eq_ignore_ascii_case(set) && c.collector_number == printing.collector_number
+            })
+            .map(|c| c.count)
+            .sum();
+        match target.checked_sub(owned) {
+            Some(missing) if missing > 0 => progress.missing.push(Card {
+                count: missing,
+                ..printing.clone()
+            }),
+            _ => progress.complete += 1,
+        }
+    }
+    progress.missing.sort_by(|a, b| {
+        rarity(a)
+            .cmp(&rarity(b))
+            .then_with(|| compare_collector_numbers(&a.collector_number, &b.collector_number))
+    });
+    progress
+}
+
+/// Cards with an unknown rarity sort last.
+fn rarity(card: &Card) -> (bool, Option<Rarity>) {
+    let rarity = Rarity::parse(&card.rarity);
+    (rarity.is_none(), rarity)
+}
+
+/// Formats `progress` as a summary line, followed by the missing cards, one
+/// section per rarity.
+pub fn format_report(progress: &Progress) -> String {
+    let unit = match progress.target {
+        1 => "cards",
+        _ => "playsets",
+    };
+    let copies: u32 = progress.missing.iter().map(|c| c.count).sum();
+    let mut output = format!(
+        "{} {}: {} of {} {unit} complete, {copies} copies missing\n",
+        progress.set.to_ascii_uppercase(),
+        progress.name,
+        progress.complete,
+        progress.size,
+    );
+
+    for (_, cards) in &progress.missing.iter().chunk_by(|c| rarity(c)) {
+        let cards: Vec<&Card> = cards.collect();
+        let copies: u32 = cards.iter().map(|c| c.count).sum();
+        let label = match cards[0].rarity.as_str() {
+            "" => "Unknown".to_string(),
+            rarity => stats::capitalize(rarity),
+        };
+        output.push_str(&format!("\n{label} ({copies}):\n"));
+        for card in cards {
+            output.push_str(&format!("  {}\n", check::format_line(card)));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::fixture_db;
+    use crate::types::Finish;
+    use pretty_assertions::assert_eq;
+
+    fn owned(number: &str, count: u32, finish: Finish) -> Card {
+        Card {
+            set: "blb".to_string(),
+            collector_number: number.to_string(),
+            count,
+            finish,
+            ..Card::default()
+        }
+    }
+
+    #[test]
+    fn test_progress() {
+        let set_cards = fixture_db("progress").set_cards("blb").unwrap().unwrap();
+        let collection = vec![
+            owned("42", 2, Finish::Nonfoil),
+            owned("42", 1, Finish::Foil),
+            owned("189", 1, Finish::Nonfoil),
+            Card {
+                set: "m11".to_string(),
+                ..owned("189", 4, Finish::Nonfoil)
+            },
+        ];
+
+        let singles = progress("BLB", &set_cards, &collection, 1);
+        assert_eq!((singles.complete, singles.size), (2, 2));
+        assert!(singles.missing.is_empty());
+
+        let playsets = progress("blb", &set_cards, &collection, PLAYSET);
+        assert_eq!(playsets.complete, 0);
+        let missing: Vec<_> = playsets
+            .missing
+            .iter()
+            .map(|c| (c.count, c.collector_number.as_str()))
+            .collect();
+        assert_eq!(missing, vec![(1, "42"), (3, "189")]);
+    }
+
+    #[test]
+    fn test_format_report() {
+        let set_cards = fixture_db("progress-report")
+            .set_cards("blb")
+            .unwrap()
+            .unwrap();
+        let report = format_report(&progress("blb", &set_cards, &[], PLAYSET));
+        assert_eq!(
+            report.lines().next().unwrap(),
+            "BLB Bloomburrow: 0 of 2 playsets complete, 8 copies missing"
+        );
+        assert!(report.contains("\nCommon (8):\n  4 Bellowing Crier (BLB) 42\n"));
+    }
+}
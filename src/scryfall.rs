@@ -1,23 +1,587 @@
+use std::cell::Cell;
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
 use anyhow::Result;
 use anyhow::anyhow;
-use reqwest::blocking::Client;
+use reqwest::blocking::{ClientBuilder, RequestBuilder, Response};
+use reqwest::{StatusCode, Url, header};
+use serde::{Deserialize, Serialize};
+
+use crate::db::CardDb;
+use crate::types::{Card, Finish};
+
+pub const SCRYFALL_API_ROOT: &str = "https://api.scryfall.com/";
+
+/// Scryfall asks for 50 to 100 milliseconds between requests, see
+/// https://scryfall.com/docs/api#rate-limits-and-good-citizenship
+const REQUEST_SPACING: Duration = Duration::from_millis(100);
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// How often a request is retried after a 429 or a server error.
+const RETRIES: u32 = 3;
+/// How long to wait before the first retry, doubled on every further one.
+/// A `Retry-After` header takes precedence.
+const BACKOFF: Duration = Duration::from_secs(1);
+
+/// The HTTP client all requests to Scryfall go through, along with the API
+/// root to send them to. Keeps requests at least `REQUEST_SPACING` apart, and
+/// retries those Scryfall asks to retry.
+pub struct Client {
+    http: reqwest::blocking::Client,
+    api_root: Url,
+    last_request: Cell<Option<Instant>>,
+    spacing: Duration,
+    backoff: Duration,
+}
+
+impl Client {
+    /// Builds a client for the API at `api_root`, usually `SCRYFALL_API_ROOT`.
+    /// Scryfall asks for an `Accept` header and a descriptive user agent.
+    pub fn new(api_root: &str) -> Result<Client> {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::ACCEPT, "application/json".parse().unwrap());
+        let http = ClientBuilder::new()
+            .user_agent("Crack-a-thon, see github.com/MordecaiMalignatus/archivist.")
+            .default_headers(headers)
+            .timeout(TIMEOUT)
+            .build()?;
+        // Without the trailing slash, `Url::join` would replace the last
+        // segment of the root instead of appending to it.
+        let api_root = match api_root.ends_with('/') {
+            true => api_root.to_string(),
+            false => format!("{api_root}/"),
+        };
+        let api_root = Url::parse(&api_root)
+            .map_err(|e| anyhow!("Invalid Scryfall API root {api_root}: {e}"))?;
+        Ok(Client {
+            http,
+            api_root,
+            last_request: Cell::new(None),
+            spacing: REQUEST_SPACING,
+            backoff: BACKOFF,
+        })
+    }
+
+    fn url(&self, path: &str) -> Result<Url> {
+        Ok(self.api_root.join(path)?)
+    }
+
+    fn get(&self, url: Url) -> Result<Response, Error> {
+        self.send(self.http.get(url))
+    }
+
+    fn post(&self, url: Url, body: &serde_json::Value) -> Result<Response, Error> {
+        self.send(self.http.post(url).json(body))
+    }
+
+    /// Sends `request`, retrying it with exponential backoff while Scryfall
+    /// answers with 429 or a server error. Any response but a successful one
+    /// is decoded into an `Error`.
+    fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let mut attempt = 0;
+        loop {
+            self.wait_for_turn();
+            // Only streamed bodies can't be cloned, and none are sent.
+            let res = request.try_clone().unwrap().send()?;
+            let status = res.status();
+            if status.is_success() {
+                return Ok(res);
+            }
+            let retry = status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+            if !retry || attempt == RETRIES {
+                return Err(Error::from_response(res));
+            }
+            thread::sleep(retry_after(&res).unwrap_or(self.backoff * 2_u32.pow(attempt)));
+            attempt += 1;
+        }
+    }
 
-use crate::types::Card;
+    /// Sleeps until `spacing` has passed since the previous request.
+    fn wait_for_turn(&self) {
+        if let Some(last) = self.last_request.get() {
+            let elapsed = last.elapsed();
+            if elapsed < self.spacing {
+                thread::sleep(self.spacing - elapsed);
+            }
+        }
+        self.last_request.set(Some(Instant::now()));
+    }
+}
+
+/// The delay a `Retry-After` header asks for, if it gives one in seconds.
+fn retry_after(res: &Response) -> Option<Duration> {
+    let seconds = res.headers().get(header::RETRY_AFTER)?.to_str().ok()?;
+    Some(Duration::from_secs(seconds.trim().parse().ok()?))
+}
 
-const SCRYFALL_API_ROOT: &str = "https://api.scryfall.com/";
+/// The error object Scryfall returns for unsuccessful responses, see
+/// https://scryfall.com/docs/api/errors
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScryfallError {
+    #[serde(default)]
+    pub status: u16,
+    /// A computer-friendly code, ie `not_found` or `bad_request`.
+    #[serde(default)]
+    pub code: String,
+    pub details: String,
+    /// More detail on the kind of error, ie `ambiguous` for fuzzy name
+    /// searches matching several cards.
+    #[serde(rename = "type")]
+    pub error_type: Option<String>,
+}
+
+/// Why a request to Scryfall failed.
+#[derive(Debug)]
+pub enum Error {
+    /// The card, set or page doesn't exist, or the search found nothing.
+    NotFound(ScryfallError),
+    /// Scryfall couldn't make sense of the request.
+    BadRequest(ScryfallError),
+    /// Scryfall kept asking to slow down, even after backing off.
+    RateLimited(ScryfallError),
+    /// Scryfall had a problem of its own, even after retrying.
+    Server(ScryfallError),
+    /// Scryfall couldn't be reached, didn't answer in time, or sent something
+    /// unreadable.
+    Http(reqwest::Error),
+}
+
+impl Error {
+    /// Decodes the error object of an unsuccessful response. Responses that
+    /// don't carry one, ie from a proxy in between, get one made up from
+    /// their status.
+    fn from_response(res: Response) -> Error {
+        let status = res.status();
+        let error = match res.json::<ScryfallError>() {
+            Ok(error) => ScryfallError {
+                status: status.as_u16(),
+                ..error
+            },
+            Err(_) => ScryfallError {
+                status: status.as_u16(),
+                code: String::new(),
+                details: status.to_string(),
+                error_type: None,
+            },
+        };
+        match (error.code.as_str(), status) {
+            ("not_found", _) | (_, StatusCode::NOT_FOUND) => Error::NotFound(error),
+            (_, StatusCode::TOO_MANY_REQUESTS) => Error::RateLimited(error),
+            (_, status) if status.is_server_error() => Error::Server(error),
+            _ => Error::BadRequest(error),
+        }
+    }
+}
 
-pub fn query_card(set: &str, number: &str, client: &Client) -> Result<Card> {
-    let url = reqwest::Url::parse(&format!("{SCRYFALL_API_ROOT}/cards/{set}/{number}"))?;
-    let req = client.get(url).build()?;
-    let res = client.execute(req)?;
-    if res.status() != 200 {
-        return Err(anyhow!(
-            "Error from Scryfall, response: {}",
-            res.text().unwrap()
-        ));
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NotFound(e) => write!(f, "Not found on Scryfall: {}", e.details),
+            Error::BadRequest(e) => write!(f, "Scryfall rejected the request: {}", e.details),
+            Error::RateLimited(e) => write!(f, "Scryfall is rate limiting requests: {}", e.details),
+            Error::Server(e) => write!(f, "Error on Scryfall's side ({}): {}", e.status, e.details),
+            Error::Http(e) => write!(f, "Could not reach Scryfall: {e}"),
+        }
     }
-    let mut card = res.json::<Card>()?;
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Error {
+        Error::Http(e)
+    }
+}
+
+/// Fetches a single printing by set code and collector number. The local card
+/// database is consulted first, Scryfall is only asked if the card is missing
+/// from it.
+pub fn query_card(set: &str, number: &str, client: &Client, db: &CardDb) -> Result<Card> {
+    if let Some(card) = db.get(set, number)? {
+        return Ok(card);
+    }
+
+    let url = client.url(&format!("cards/{set}/{number}"))?;
+    let mut card = client.get(url)?.json::<Card>()?;
     card.count = 1;
-    card.foil = false;
+    card.finish = Finish::Nonfoil;
     Ok(card)
 }
+
+/// How many cards Scryfall's `/cards/collection` accepts per request.
+const COLLECTION_BATCH_SIZE: usize = 75;
+
+#[derive(Debug, Serialize)]
+struct CollectionIdentifier<'a> {
+    set: &'a str,
+    collector_number: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct CardList {
+    data: Vec<Card>,
+}
+
+/// Fetches many printings, given as `(set, collector_number)`, in as few
+/// requests as possible via https://scryfall.com/docs/api/cards/collection.
+/// Printings Scryfall doesn't know are left out of the result.
+pub fn query_collection(printings: &[(String, String)], client: &Client) -> Result<Vec<Card>> {
+    let url = client.url("cards/collection")?;
+    let mut cards = Vec::new();
+    for batch in printings.chunks(COLLECTION_BATCH_SIZE) {
+        let identifiers: Vec<_> = batch
+            .iter()
+            .map(|(set, number)| CollectionIdentifier {
+                set,
+                collector_number: number,
+            })
+            .collect();
+        let res = client.post(
+            url.clone(),
+            &serde_json::json!({ "identifiers": identifiers }),
+        )?;
+        cards.extend(res.json::<CardList>()?.data);
+    }
+    Ok(cards)
+}
+
+/// A set as Scryfall describes it, see https://scryfall.com/docs/api/sets
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetInfo {
+    pub code: String,
+    /// How many printings the set has.
+    pub card_count: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct SetList {
+    data: Vec<SetInfo>,
+}
+
+/// Fetches every set Scryfall knows, in a single request.
+pub fn query_sets(client: &Client) -> Result<Vec<SetInfo>> {
+    Ok(client.get(client.url("sets")?)?.json::<SetList>()?.data)
+}
+
+/// One page of search results, see https://scryfall.com/docs/api/lists
+#[derive(Debug, Deserialize)]
+struct SearchPage {
+    data: Vec<Card>,
+    #[serde(default)]
+    next_page: Option<String>,
+}
+
+/// Fetches every printing in `set`. The local card database is consulted
+/// first, Scryfall's search is only used if the set is missing from it.
+pub fn query_set_cards(set: &str, client: &Client, db: &CardDb) -> Result<Vec<Card>> {
+    if let Some(cards) = db.set_cards(set)? {
+        return Ok(cards);
+    }
+
+    let mut url = client.url("cards/search")?;
+    url.query_pairs_mut()
+        .append_pair("q", &format!("e:{set}"))
+        .append_pair("unique", "prints")
+        .append_pair("order", "set");
+    let mut cards = Vec::new();
+    loop {
+        let page = client.get(url)?.json::<SearchPage>()?;
+        cards.extend(page.data.into_iter().map(|mut card| {
+            card.count = 1;
+            card.finish = Finish::Nonfoil;
+            card
+        }));
+        match page.next_page {
+            Some(next) => url = Url::parse(&next)?,
+            None => return Ok(cards),
+        }
+    }
+}
+
+/// The result of looking a card up by name.
+#[derive(Debug)]
+pub enum NameMatch {
+    Card(Box<Card>),
+    /// The name matched several different cards, these are their names.
+    Ambiguous(Vec<String>),
+    /// The name matched a single card, but several printings of it.
+    Printings(Vec<Card>),
+}
+
+#[derive(Debug, Deserialize)]
+struct Catalog {
+    data: Vec<String>,
+}
+
+/// Looks a card up by (partial) name, optionally restricted to one set. The
+/// local card database is consulted first, if it has no match, Scryfall's fuzzy
+/// name search is used.
+pub fn query_card_by_name(
+    name: &str,
+    set: Option<&str>,
+    client: &Client,
+    db: &CardDb,
+) -> Result<NameMatch> {
+    let mut printings = db.find_by_name(name, set)?;
+    let mut names: Vec<String> = printings.iter().map(|c| c.name.clone()).collect();
+    names.sort();
+    names.dedup();
+    match names.len() {
+        0 => {}
+        1 if printings.len() == 1 => return Ok(NameMatch::Card(Box::new(printings.remove(0)))),
+        1 => {
+            let number = |c: &Card| c.collector_number.parse::<u32>().unwrap_or(u32::MAX);
+            printings.sort_by(|a, b| a.set.cmp(&b.set).then(number(a).cmp(&number(b))));
+            return Ok(NameMatch::Printings(printings));
+        }
+        _ => return Ok(NameMatch::Ambiguous(names)),
+    }
+
+    let mut url = client.url("cards/named")?;
+    url.query_pairs_mut().append_pair("fuzzy", name);
+    if let Some(set) = set {
+        url.query_pairs_mut().append_pair("set", set);
+    }
+    match client.get(url) {
+        Ok(res) => {
+            let mut card = res.json::<Card>()?;
+            card.count = 1;
+            card.finish = Finish::Nonfoil;
+            Ok(NameMatch::Card(Box::new(card)))
+        }
+        Err(Error::NotFound(error)) if error.error_type.as_deref() == Some("ambiguous") => {
+            Ok(NameMatch::Ambiguous(autocomplete(name, client)?))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Asks Scryfall for up to 20 card names starting with `partial_name`.
+fn autocomplete(partial_name: &str, client: &Client) -> Result<Vec<String>> {
+    let mut url = client.url("cards/autocomplete")?;
+    url.query_pairs_mut().append_pair("q", partial_name);
+    Ok(client.get(url)?.json::<Catalog>()?.data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::fixture_db;
+    use pretty_assertions::assert_eq;
+
+    /// A client for `server` that doesn't make tests wait.
+    fn mock_client(server: &mockito::Server) -> Client {
+        let mut client = Client::new(&server.url()).unwrap();
+        client.spacing = Duration::ZERO;
+        client.backoff = Duration::from_millis(1);
+        client
+    }
+
+    /// The Scryfall card object of Lightning Bolt from the fixture bulk file.
+    fn bolt_json() -> String {
+        let fixture = include_str!("../fixtures/default_cards.json");
+        let cards: Vec<serde_json::Value> = serde_json::from_str(fixture).unwrap();
+        let bolt = cards.iter().find(|c| c["name"] == "Lightning Bolt");
+        bolt.unwrap().to_string()
+    }
+
+    fn error_json(status: u16, code: &str, details: &str) -> String {
+        serde_json::json!({
+            "object": "error",
+            "status": status,
+            "code": code,
+            "details": details,
+        })
+        .to_string()
+    }
+
+    fn query_error(client: &Client, db: &CardDb) -> Error {
+        let e = query_card("m11", "150", client, db).unwrap_err();
+        e.downcast::<Error>().unwrap()
+    }
+
+    #[test]
+    fn test_query_card_uses_local_db() {
+        let db = fixture_db("query-card");
+        // The fixture contains this card, so this never has to reach Scryfall.
+        let client = Client::new(SCRYFALL_API_ROOT).unwrap();
+
+        let card = query_card("m11", "149", &client, &db).unwrap();
+        assert_eq!(card.name, "Lightning Bolt");
+        assert_eq!(card.count, 1);
+    }
+
+    #[test]
+    fn test_query_card_by_name_uses_local_db() {
+        let db = fixture_db("query-card-by-name");
+        let client = Client::new(SCRYFALL_API_ROOT).unwrap();
+
+        match query_card_by_name("lightning bolt", None, &client, &db).unwrap() {
+            NameMatch::Card(card) => assert_eq!(card.collector_number, "149"),
+            other => panic!("expected a single card, got {other:?}"),
+        }
+        match query_card_by_name("l", Some("blb"), &client, &db).unwrap() {
+            NameMatch::Ambiguous(names) => {
+                assert_eq!(names, vec!["Bellowing Crier", "Polliwallop"])
+            }
+            other => panic!("expected several names, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_query_card_from_scryfall() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("GET", "/cards/m11/150")
+            .match_header("accept", "application/json")
+            .with_body(bolt_json())
+            .create();
+        let db = fixture_db("mock-query-card");
+
+        let card = query_card("m11", "150", &mock_client(&server), &db).unwrap();
+        assert_eq!(card.name, "Lightning Bolt");
+        assert_eq!(card.count, 1);
+        mock.assert();
+    }
+
+    #[test]
+    fn test_errors_are_typed() {
+        let mut server = mockito::Server::new();
+        let client = mock_client(&server);
+        let db = fixture_db("mock-errors");
+
+        let mock = server
+            .mock("GET", "/cards/m11/150")
+            .with_status(404)
+            .with_body(error_json(404, "not_found", "No card found."))
+            .create();
+        match query_error(&client, &db) {
+            Error::NotFound(e) => assert_eq!(e.details, "No card found."),
+            other => panic!("expected not found, got {other:?}"),
+        }
+        mock.remove();
+
+        let mock = server
+            .mock("GET", "/cards/m11/150")
+            .with_status(400)
+            .with_body(error_json(400, "bad_request", "Invalid set."))
+            .create();
+        let error = query_error(&client, &db);
+        assert!(matches!(error, Error::BadRequest(_)));
+        assert_eq!(
+            error.to_string(),
+            "Scryfall rejected the request: Invalid set."
+        );
+        mock.remove();
+
+        // Not every error comes with an error object, ie from a proxy.
+        let mock = server
+            .mock("GET", "/cards/m11/150")
+            .with_status(502)
+            .with_body("<html>Bad Gateway</html>")
+            .expect(RETRIES as usize + 1)
+            .create();
+        let error = query_error(&client, &db);
+        assert_eq!(
+            error.to_string(),
+            "Error on Scryfall's side (502): 502 Bad Gateway"
+        );
+        mock.assert();
+    }
+
+    #[test]
+    fn test_retries_with_backoff() {
+        let mut server = mockito::Server::new();
+        let unavailable = server
+            .mock("GET", "/cards/m11/150")
+            .with_status(503)
+            .with_body(error_json(503, "unavailable", "Try again."))
+            .expect(1)
+            .create();
+        let rate_limited = server
+            .mock("GET", "/cards/m11/150")
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .with_body(error_json(429, "too_many_requests", "Slow down."))
+            .expect(1)
+            .create();
+        let ok = server
+            .mock("GET", "/cards/m11/150")
+            .with_body(bolt_json())
+            .expect(1)
+            .create();
+        let db = fixture_db("mock-retries");
+
+        let card = query_card("m11", "150", &mock_client(&server), &db).unwrap();
+        assert_eq!(card.name, "Lightning Bolt");
+        unavailable.assert();
+        rate_limited.assert();
+        ok.assert();
+    }
+
+    #[test]
+    fn test_requests_are_spaced() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("GET", "/sets")
+            .with_body(r#"{"data": [{"code": "blb", "card_count": 281}]}"#)
+            .expect(3)
+            .create();
+        let mut client = mock_client(&server);
+        client.spacing = Duration::from_millis(50);
+
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(query_sets(&client).unwrap()[0].card_count, 281);
+        }
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        mock.assert();
+    }
+
+    #[test]
+    fn test_ambiguous_name_falls_back_to_autocomplete() {
+        let mut server = mockito::Server::new();
+        let named = server
+            .mock("GET", "/cards/named")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "fuzzy".to_string(),
+                "goblin".to_string(),
+            ))
+            .with_status(404)
+            .with_body(
+                serde_json::json!({
+                    "object": "error",
+                    "status": 404,
+                    "code": "not_found",
+                    "type": "ambiguous",
+                    "details": "Too many cards match ambiguous name “goblin”.",
+                })
+                .to_string(),
+            )
+            .create();
+        let autocomplete = server
+            .mock("GET", "/cards/autocomplete")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "q".to_string(),
+                "goblin".to_string(),
+            ))
+            .with_body(r#"{"data": ["Goblin Guide", "Goblin Lackey"]}"#)
+            .create();
+        let db = fixture_db("mock-ambiguous");
+
+        match query_card_by_name("goblin", None, &mock_client(&server), &db).unwrap() {
+            NameMatch::Ambiguous(names) => assert_eq!(names, vec!["Goblin Guide", "Goblin Lackey"]),
+            other => panic!("expected several names, got {other:?}"),
+        }
+        named.assert();
+        autocomplete.assert();
+    }
+}
@@ -0,0 +1,540 @@
+use std::cmp::Ordering;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use rust_decimal::Decimal;
+
+use crate::types::{Card, Condition, Finish};
+
+/// A parsed search query. This is modeled after Scryfall's search syntax, see
+/// https://scryfall.com/docs/syntax, but only covers the fields that are
+/// actually stored in an archive.
+///
+/// - `bolt`, `name:bolt` or `name:"lightning bolt"` match on card names.
+/// - `set:blb`, `s:blb` or `e:blb` match on the set code.
+/// - `r:rare`, `r>=rare` match on rarity.
+/// - `c:ug`, `c=w`, `c<=wb` match on colours, `c:c` is colourless, `c:m` multicoloured.
+/// - `foil`, `is:foil` and `is:nonfoil` match on any kind of foil,
+///   `finish:etched` or `is:etched` on a specific finish.
+/// - `lang:ja` matches on language, `cond:lp` or `cond>=mp` on condition.
+/// - `count>2`, `usd>1.00`, `eur<=0.5` compare numbers.
+///
+/// Terms are joined with `and` (which is also implied between two terms),
+/// `or`, negated with a leading `-`, and can be grouped with parentheses.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    Term(Term),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Name(Op, String),
+    Set(String),
+    Number(Op, String),
+    Rarity(Op, Rarity),
+    Colors(Op, Vec<char>),
+    Multicolor,
+    Foil(bool),
+    Finish(Finish),
+    Language(String),
+    Condition(Op, Condition),
+    Count(Op, f64),
+    Usd(Op, Decimal),
+    Eur(Op, Decimal),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// `:`, the "natural" comparison for a field: substring for names, equality
+    /// for sets and rarities, superset for colours.
+    Matches,
+    Eq,
+    NotEq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    Special,
+    Mythic,
+    Bonus,
+}
+
+impl Rarity {
+    pub fn parse(s: &str) -> Option<Rarity> {
+        match s.to_ascii_lowercase().as_str() {
+            "c" | "common" => Some(Rarity::Common),
+            "u" | "uncommon" => Some(Rarity::Uncommon),
+            "r" | "rare" => Some(Rarity::Rare),
+            "s" | "special" => Some(Rarity::Special),
+            "m" | "mythic" => Some(Rarity::Mythic),
+            "b" | "bonus" => Some(Rarity::Bonus),
+            _ => None,
+        }
+    }
+}
+
+impl Op {
+    fn compare(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Matches | Op::Eq => ordering == Ordering::Equal,
+            Op::NotEq => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Lte => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Gte => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// Operators in the order they need to be tried in, so that `>=` is not read
+/// as `>` followed by a value starting with `=`.
+const OPERATORS: [(&str, Op); 7] = [
+    (">=", Op::Gte),
+    ("<=", Op::Lte),
+    ("!=", Op::NotEq),
+    (":", Op::Matches),
+    ("=", Op::Eq),
+    (">", Op::Gt),
+    ("<", Op::Lt),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Minus,
+    Word(String),
+    /// A fully quoted word, which always is a name search.
+    Quoted(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '-' => {
+                chars.next();
+                tokens.push(Token::Minus);
+            }
+            _ => {
+                let mut word = String::new();
+                let fully_quoted = c == '"';
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    chars.next();
+                    if c == '"' {
+                        loop {
+                            match chars.next() {
+                                Some('"') => break,
+                                Some(c) => word.push(c),
+                                None => {
+                                    return Err(anyhow!("Unterminated quote in query: {input}"));
+                                }
+                            }
+                        }
+                    } else {
+                        word.push(c);
+                    }
+                }
+                tokens.push(match fully_quoted {
+                    true => Token::Quoted(word),
+                    false => Token::Word(word),
+                });
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parse a query string into a `Query`. Fails on unknown fields, malformed
+/// values and unbalanced parentheses.
+pub fn parse_query(input: &str) -> Result<Query> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Err(anyhow!("Empty search query."));
+    }
+    let mut parser = QueryParser {
+        tokens,
+        position: 0,
+    };
+    let query = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(query),
+        Some(token) => Err(anyhow!("Unexpected {token:?} in query.")),
+    }
+}
+
+struct QueryParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl QueryParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn parse_or(&mut self) -> Result<Query> {
+        let mut query = self.parse_and()?;
+        while self.peek_keyword("or") {
+            self.next();
+            let rhs = self.parse_and()?;
+            query = Query::Or(Box::new(query), Box::new(rhs));
+        }
+        Ok(query)
+    }
+
+    fn parse_and(&mut self) -> Result<Query> {
+        let mut query = self.parse_unary()?;
+        loop {
+            if self.peek_keyword("and") {
+                self.next();
+            } else if self.peek().is_none()
+                || self.peek() == Some(&Token::RParen)
+                || self.peek_keyword("or")
+            {
+                break;
+            }
+            let rhs = self.parse_unary()?;
+            query = Query::And(Box::new(query), Box::new(rhs));
+        }
+        Ok(query)
+    }
+
+    fn parse_unary(&mut self) -> Result<Query> {
+        match self.next() {
+            Some(Token::Minus) => Ok(Query::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LParen) => {
+                let query = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(query),
+                    _ => Err(anyhow!("Missing closing parenthesis in query.")),
+                }
+            }
+            Some(Token::Quoted(name)) => Ok(Query::Term(Term::Name(Op::Matches, name))),
+            Some(Token::Word(word)) => Ok(Query::Term(parse_term(&word)?)),
+            Some(Token::RParen) => Err(anyhow!("Unexpected closing parenthesis in query.")),
+            None => Err(anyhow!("Query ended unexpectedly.")),
+        }
+    }
+}
+
+fn parse_term(word: &str) -> Result<Term> {
+    let split = word.char_indices().find_map(|(i, _)| {
+        OPERATORS
+            .iter()
+            .find(|(op, _)| word[i..].starts_with(op))
+            .map(|(op, kind)| (i, op.len(), *kind))
+    });
+
+    let Some((index, op_len, op)) = split else {
+        return Ok(match word.to_ascii_lowercase().as_str() {
+            "foil" => Term::Foil(true),
+            "nonfoil" => Term::Foil(false),
+            _ => Term::Name(Op::Matches, word.to_string()),
+        });
+    };
+    let key = word[..index].to_ascii_lowercase();
+    let value = &word[index + op_len..];
+    if value.is_empty() {
+        return Err(anyhow!("Missing value for `{key}` in query."));
+    }
+
+    let term = match key.as_str() {
+        "name" | "n" => Term::Name(op, value.to_string()),
+        "set" | "s" | "e" | "edition" => match op {
+            Op::Matches | Op::Eq => Term::Set(value.to_ascii_lowercase()),
+            _ => return Err(anyhow!("Sets can only be compared with `:` or `=`.")),
+        },
+        "number" | "cn" => Term::Number(op, value.to_string()),
+        "r" | "rarity" => match Rarity::parse(value) {
+            Some(rarity) => Term::Rarity(op, rarity),
+            None => return Err(anyhow!("Unknown rarity in query: {value}")),
+        },
+        "c" | "color" | "colour" => match value.to_ascii_lowercase().as_str() {
+            "m" | "multicolor" | "multicolour" => Term::Multicolor,
+            v => Term::Colors(op, parse_colors(v)?),
+        },
+        "is" => match value.to_ascii_lowercase().as_str() {
+            "foil" => Term::Foil(true),
+            "nonfoil" => Term::Foil(false),
+            v => match Finish::parse(v) {
+                Some(finish) => Term::Finish(finish),
+                None => return Err(anyhow!("Unknown `is:` filter in query: {value}")),
+            },
+        },
+        "finish" => match Finish::parse(value) {
+            Some(finish) => Term::Finish(finish),
+            None => return Err(anyhow!("Unknown finish in query: {value}")),
+        },
+        "lang" | "language" => Term::Language(value.to_ascii_lowercase()),
+        "cond" | "condition" => match Condition::parse(value) {
+            Some(condition) => Term::Condition(op, condition),
+            None => return Err(anyhow!("Unknown condition in query: {value}")),
+        },
+        "count" => Term::Count(op, parse_number(value)?),
+        "usd" => Term::Usd(op, parse_number(value)?),
+        "eur" => Term::Eur(op, parse_number(value)?),
+        _ => return Err(anyhow!("Unknown search field: {key}")),
+    };
+
+    Ok(term)
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("Expected a number in query, got: {value}"))
+}
+
+/// Turns `ug`, `blue`, or `colorless` into a sorted list of colour letters, as
+/// used by Scryfall in `Card.colors`.
+fn parse_colors(value: &str) -> Result<Vec<char>> {
+    let letters = match value {
+        "white" => "w",
+        "blue" => "u",
+        "black" => "b",
+        "red" => "r",
+        "green" => "g",
+        "c" | "colorless" | "colourless" => "",
+        v => v,
+    };
+    let mut colors = Vec::new();
+    for c in letters.chars() {
+        match c {
+            'w' | 'u' | 'b' | 'r' | 'g' => colors.push(c.to_ascii_uppercase()),
+            _ => return Err(anyhow!("Unknown colour in query: {value}")),
+        }
+    }
+    colors.sort_unstable();
+    colors.dedup();
+    Ok(colors)
+}
+
+impl Query {
+    pub fn matches(&self, card: &Card) -> bool {
+        match self {
+            Query::And(lhs, rhs) => lhs.matches(card) && rhs.matches(card),
+            Query::Or(lhs, rhs) => lhs.matches(card) || rhs.matches(card),
+            Query::Not(query) => !query.matches(card),
+            Query::Term(term) => term.matches(card),
+        }
+    }
+}
+
+impl Term {
+    fn matches(&self, card: &Card) -> bool {
+        match self {
+            Term::Name(Op::Matches, name) => {
+                card.name.to_lowercase().contains(&name.to_lowercase())
+            }
+            Term::Name(op, name) => op.compare(card.name.to_lowercase().cmp(&name.to_lowercase())),
+            Term::Set(set) => card.set.eq_ignore_ascii_case(set),
+            Term::Number(op, number) => {
+                let ordering = match (card.collector_number.parse::<u32>(), number.parse::<u32>()) {
+                    (Ok(lhs), Ok(rhs)) => lhs.cmp(&rhs),
+                    _ => card.collector_number.cmp(number),
+                };
+                op.compare(ordering)
+            }
+            Term::Rarity(op, rarity) => match Rarity::parse(&card.rarity) {
+                Some(card_rarity) => op.compare(card_rarity.cmp(rarity)),
+                None => false,
+            },
+            Term::Colors(op, colors) => {
+                let card_colors: Vec<char> = card
+                    .colors
+                    .iter()
+                    .flatten()
+                    .filter_map(|c| c.chars().next())
+                    .collect();
+                let is_superset = colors.iter().all(|c| card_colors.contains(c));
+                let is_subset = card_colors.iter().all(|c| colors.contains(c));
+                match op {
+                    // `c:` on Scryfall means "at least these colours", except
+                    // for colourless, which has to be exact.
+                    Op::Matches if colors.is_empty() => card_colors.is_empty(),
+                    Op::Matches | Op::Gte => is_superset,
+                    Op::Eq => is_superset && is_subset,
+                    Op::NotEq => !(is_superset && is_subset),
+                    Op::Lte => is_subset,
+                    Op::Gt => is_superset && !is_subset,
+                    Op::Lt => is_subset && !is_superset,
+                }
+            }
+            Term::Multicolor => card.colors.as_ref().is_some_and(|c| c.len() > 1),
+            Term::Foil(foil) => card.finish.is_foil() == *foil,
+            Term::Finish(finish) => card.finish == *finish,
+            Term::Language(language) => card.language == *language,
+            Term::Condition(op, condition) => op.compare(card.condition.cmp(condition)),
+            Term::Count(op, count) => compare_number(*op, card.count as f64, *count),
+            Term::Usd(op, usd) => card.usd().is_some_and(|p| op.compare(p.amount.cmp(usd))),
+            Term::Eur(op, eur) => card.eur().is_some_and(|p| op.compare(p.amount.cmp(eur))),
+        }
+    }
+}
+
+fn compare_number(op: Op, lhs: f64, rhs: f64) -> bool {
+    match lhs.partial_cmp(&rhs) {
+        Some(ordering) => op.compare(ordering),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::CardPrices;
+
+    fn card(name: &str, set: &str, rarity: &str, colors: &[&str], foil: bool) -> Card {
+        let finish = match foil {
+            true => Finish::Foil,
+            false => Finish::Nonfoil,
+        };
+        Card {
+            name: name.to_string(),
+            collector_number: "12".to_string(),
+            set: set.to_string(),
+            rarity: rarity.to_string(),
+            colors: Some(colors.iter().map(|c| c.to_string()).collect()),
+            finish,
+            language: "en".to_string(),
+            count: 1,
+            prices: Some(CardPrices {
+                usd: Some(Decimal::new(50, 2)),
+                usd_foil: Some(Decimal::new(200, 2)),
+                eur: Some(Decimal::new(40, 2)),
+                eur_foil: None,
+                ..CardPrices::default()
+            }),
+            ..Card::default()
+        }
+    }
+
+    fn search(query: &str, card: &Card) -> bool {
+        parse_query(query).unwrap().matches(card)
+    }
+
+    #[test]
+    fn test_name_search() {
+        let bolt = card("Lightning Bolt", "m11", "common", &["R"], false);
+        assert!(search("bolt", &bolt));
+        assert!(search("name:\"lightning bolt\"", &bolt));
+        assert!(search("\"Lightning Bolt\"", &bolt));
+        assert!(!search("name:shock", &bolt));
+    }
+
+    #[test]
+    fn test_field_search() {
+        let card = card("Polliwallop", "blb", "common", &["G"], true);
+        assert!(search("set:blb", &card));
+        assert!(search("e:BLB", &card));
+        assert!(search("r:common", &card));
+        assert!(!search("r>=rare", &card));
+        assert!(search("foil", &card));
+        assert!(!search("is:nonfoil", &card));
+        assert!(search("count>=1", &card));
+        assert!(!search("count>2", &card));
+        assert!(search("cn:12", &card));
+    }
+
+    #[test]
+    fn test_finish_language_condition_search() {
+        let mut card = card("Polliwallop", "blb", "common", &["G"], false);
+        card.finish = Finish::Etched;
+        card.language = "ja".to_string();
+        card.condition = Condition::ModeratelyPlayed;
+        assert!(search("foil", &card));
+        assert!(search("is:etched", &card));
+        assert!(!search("finish:foil", &card));
+        assert!(search("lang:JA", &card));
+        assert!(search("cond:mp", &card));
+        assert!(search("cond>lp", &card));
+        assert!(!search("cond>=hp", &card));
+    }
+
+    #[test]
+    fn test_color_search() {
+        let simic = card("Tamiyo", "blb", "mythic", &["G", "U"], false);
+        assert!(search("c:ug", &simic));
+        assert!(search("c:u", &simic));
+        assert!(!search("c=u", &simic));
+        assert!(search("c<=wubg", &simic));
+        assert!(search("c:m", &simic));
+        assert!(!search("c:c", &simic));
+        assert!(search(
+            "c:c",
+            &card("Sol Ring", "cmm", "uncommon", &[], false)
+        ));
+    }
+
+    #[test]
+    fn test_price_search() {
+        let nonfoil = card("Shock", "m21", "common", &["R"], false);
+        let foil = card("Shock", "m21", "common", &["R"], true);
+        assert!(search("usd>0.25", &nonfoil));
+        assert!(!search("usd>1.00", &nonfoil));
+        assert!(search("usd>1.00", &foil));
+        // There is no foil EUR price, so this can't match either way.
+        assert!(!search("eur>0", &foil));
+        assert!(!search("eur<=0", &foil));
+    }
+
+    #[test]
+    fn test_boolean_operators() {
+        let card = card("Polliwallop", "blb", "common", &["G"], false);
+        assert!(search("set:blb and c:g", &card));
+        assert!(search("set:dsk or c:g", &card));
+        assert!(!search("set:blb -c:g", &card));
+        assert!(search("-(set:dsk or foil)", &card));
+        assert!(!search("set:dsk or c:u r:common", &card));
+    }
+
+    #[test]
+    fn test_invalid_queries() {
+        assert!(parse_query("").is_err());
+        assert!(parse_query("(set:blb").is_err());
+        assert!(parse_query("set:blb)").is_err());
+        assert!(parse_query("frobnicate:3").is_err());
+        assert!(parse_query("r:legendary").is_err());
+        assert!(parse_query("usd>cheap").is_err());
+        assert!(parse_query("c:purple").is_err());
+        assert!(parse_query("name:\"unterminated").is_err());
+    }
+}
@@ -0,0 +1,545 @@
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+
+use anyhow::Result;
+use anyhow::anyhow;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Bar, BarChart, BarGroup, Block, Tabs};
+use rust_decimal::Decimal;
+
+use crate::search::Rarity;
+use crate::types::{Card, Currency, Finish, Price};
+
+const HELP_TEXT: &str = "q quit  tab/l next  shift-tab/h previous";
+
+/// What the copies of a collection are grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    Set,
+    Rarity,
+    Color,
+    Finish,
+    Price,
+}
+
+impl Dimension {
+    pub const ALL: [Dimension; 5] = [
+        Dimension::Set,
+        Dimension::Rarity,
+        Dimension::Color,
+        Dimension::Finish,
+        Dimension::Price,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Dimension::Set => "Set",
+            Dimension::Rarity => "Rarity",
+            Dimension::Color => "Color",
+            Dimension::Finish => "Finish",
+            Dimension::Price => "Price",
+        }
+    }
+}
+
+/// The copies that fall into one group, and what they're worth.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bucket {
+    pub label: String,
+    pub copies: u32,
+    /// The value of the copies in the preferred currency. Copies without a
+    /// price add nothing.
+    pub value: Decimal,
+}
+
+/// How many of the printings of a set the collection has at least one copy of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetCompletion {
+    pub set: String,
+    pub name: String,
+    pub owned: usize,
+    /// How many printings the set has. `None` if it couldn't be looked up.
+    pub size: Option<usize>,
+}
+
+impl SetCompletion {
+    pub fn percent(&self) -> Option<f64> {
+        self.size
+            .filter(|&size| size > 0)
+            .map(|size| self.owned as f64 * 100.0 / size as f64)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub currency: Currency,
+    pub copies: u32,
+    pub printings: usize,
+    pub value: Decimal,
+    pub unpriced: u32,
+    buckets: Vec<(Dimension, Vec<Bucket>)>,
+    pub completion: Vec<SetCompletion>,
+}
+
+impl Stats {
+    pub fn buckets(&self, dimension: Dimension) -> &[Bucket] {
+        self.buckets
+            .iter()
+            .find(|(d, _)| *d == dimension)
+            .map(|(_, buckets)| buckets.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+/// The upper bounds of the price bands, per copy, in the preferred currency,
+/// as `Decimal::new` arguments. Everything above the last one is a band of its
+/// own.
+const PRICE_BANDS: [(i64, u32); 5] = [(25, 2), (1, 0), (5, 0), (20, 0), (100, 0)];
+
+/// Aggregates `cards` by every `Dimension`, valuing them in `currency`, and
+/// works out how complete each set is against `set_sizes`, keyed by set code.
+/// Entries without copies are left out.
+pub fn stats(cards: &[Card], currency: Currency, set_sizes: &BTreeMap<String, usize>) -> Stats {
+    let cards: Vec<&Card> = cards.iter().filter(|c| c.count > 0).collect();
+    let worth = |card: &Card| {
+        card.price(currency)
+            .map(|p| p.amount * Decimal::from(card.count))
+    };
+
+    let mut stats = Stats {
+        currency,
+        copies: cards.iter().map(|c| c.count).sum(),
+        printings: 0,
+        value: cards.iter().filter_map(|c| worth(c)).sum(),
+        unpriced: cards
+            .iter()
+            .filter(|c| c.price(currency).is_none())
+            .map(|c| c.count)
+            .sum(),
+        buckets: Vec::new(),
+        completion: Vec::new(),
+    };
+
+    for dimension in Dimension::ALL {
+        // Buckets are keyed by something that sorts them in display order.
+        let mut buckets: BTreeMap<(usize, String), Bucket> = BTreeMap::new();
+        for card in &cards {
+            let (order, label) = match dimension {
+                Dimension::Set => (0, set_label(card)),
+                Dimension::Rarity => rarity_label(card),
+                Dimension::Color => color_label(card),
+                Dimension::Finish => finish_label(card.finish),
+                Dimension::Price => price_band(card.price(currency), currency),
+            };
+            let bucket = buckets
+                .entry((order, label.clone()))
+                .or_insert_with(|| Bucket {
+                    label,
+                    copies: 0,
+                    value: Decimal::ZERO,
+                });
+            bucket.copies += card.count;
+            bucket.value += worth(card).unwrap_or_default();
+        }
+        let mut buckets: Vec<Bucket> = buckets.into_values().collect();
+        if dimension == Dimension::Set {
+            buckets.sort_by(|a, b| b.copies.cmp(&a.copies).then_with(|| a.label.cmp(&b.label)));
+        }
+        stats.buckets.push((dimension, buckets));
+    }
+
+    let mut printings: BTreeMap<&str, (&str, Vec<&str>)> = BTreeMap::new();
+    for card in &cards {
+        let (_, numbers) = printings
+            .entry(&card.set)
+            .or_insert((&card.set_name, Vec::new()));
+        numbers.push(&card.collector_number);
+    }
+    for (set, (name, mut numbers)) in printings {
+        numbers.sort();
+        numbers.dedup();
+        stats.printings += numbers.len();
+        stats.completion.push(SetCompletion {
+            set: set.to_string(),
+            name: name.to_string(),
+            owned: numbers.len(),
+            size: set_sizes.get(set).copied(),
+        });
+    }
+    stats.completion.sort_by(|a, b| {
+        let percent = |c: &SetCompletion| c.percent().unwrap_or(-1.0);
+        percent(b)
+            .total_cmp(&percent(a))
+            .then_with(|| a.set.cmp(&b.set))
+    });
+    stats
+}
+
+fn set_label(card: &Card) -> String {
+    format!("{} {}", card.set.to_ascii_uppercase(), card.set_name)
+        .trim_end()
+        .to_string()
+}
+
+fn rarity_label(card: &Card) -> (usize, String) {
+    match Rarity::parse(&card.rarity) {
+        Some(rarity) => (rarity as usize, capitalize(&card.rarity)),
+        None => (usize::MAX, "Unknown".to_string()),
+    }
+}
+
+/// Groups cards by their colors, multicolored ones all together. Cards from
+/// archives from before colors were recorded go into `Unknown`.
+fn color_label(card: &Card) -> (usize, String) {
+    const COLORS: [(&str, &str); 5] = [
+        ("W", "White"),
+        ("U", "Blue"),
+        ("B", "Black"),
+        ("R", "Red"),
+        ("G", "Green"),
+    ];
+    let label = match card.colors.as_deref() {
+        None => "Unknown",
+        Some([]) => "Colorless",
+        Some([color]) => match COLORS.iter().find(|(code, _)| code == color) {
+            Some((_, name)) => name,
+            None => "Unknown",
+        },
+        Some(_) => "Multicolor",
+    };
+    let order = [
+        "White",
+        "Blue",
+        "Black",
+        "Red",
+        "Green",
+        "Multicolor",
+        "Colorless",
+    ]
+    .iter()
+    .position(|l| *l == label)
+    .unwrap_or(usize::MAX);
+    (order, label.to_string())
+}
+
+fn finish_label(finish: Finish) -> (usize, String) {
+    let order = Finish::ALL.iter().position(|f| *f == finish).unwrap();
+    (order, capitalize(finish.name()))
+}
+
+/// Which price band a copy costing `price` falls into, see `PRICE_BANDS`.
+fn price_band(price: Option<Price>, currency: Currency) -> (usize, String) {
+    let Some(price) = price else {
+        return (usize::MAX, "No price".to_string());
+    };
+    let mut lower = None;
+    for (i, (amount, scale)) in PRICE_BANDS.into_iter().enumerate() {
+        let upper = Price {
+            amount: Decimal::new(amount, scale),
+            currency,
+        };
+        if price.amount < upper.amount {
+            let label = match lower {
+                None => format!("Under {upper}"),
+                Some(lower) => format!("{lower} to {upper}"),
+            };
+            return (i, label);
+        }
+        lower = Some(upper);
+    }
+    (PRICE_BANDS.len(), format!("{} and up", lower.unwrap()))
+}
+
+pub fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn format_value(amount: Decimal, currency: Currency) -> String {
+    Price {
+        amount: amount.round_dp(2),
+        currency,
+    }
+    .to_string()
+}
+
+/// Formats `stats` as a table per dimension, followed by the completion of
+/// every set.
+pub fn format_report(stats: &Stats) -> String {
+    let mut output = format!(
+        "{} copies of {} printings, worth {}\n",
+        stats.copies,
+        stats.printings,
+        format_value(stats.value, stats.currency)
+    );
+    if stats.unpriced > 0 {
+        output.push_str(&format!("{} copies have no price.\n", stats.unpriced));
+    }
+
+    for dimension in Dimension::ALL {
+        output.push_str(&format!("\nBy {}:\n", dimension.title().to_lowercase()));
+        for bucket in stats.buckets(dimension) {
+            output.push_str(&format!(
+                "  {:<30}{:>7}{:>12}\n",
+                bucket.label,
+                bucket.copies,
+                format_value(bucket.value, stats.currency)
+            ));
+        }
+    }
+
+    if !stats.completion.is_empty() {
+        output.push_str("\nSet completion:\n");
+    }
+    for set in &stats.completion {
+        let label = format!("{} {}", set.set.to_ascii_uppercase(), set.name);
+        let (size, percent) = match (set.size, set.percent()) {
+            (Some(size), Some(percent)) => (size.to_string(), format!("{percent:.1}%")),
+            _ => ("?".to_string(), String::new()),
+        };
+        output.push_str(&format!(
+            "  {:<30}{:>11}{:>8}\n",
+            label.trim_end(),
+            format!("{}/{size}", set.owned),
+            percent
+        ));
+    }
+    output
+}
+
+/// Shows `stats` as bar charts, one tab per dimension and one for set
+/// completion.
+pub fn chart(stats: &Stats) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        return Err(anyhow!("Can't show a chart, stdout is not a terminal."));
+    }
+    let mut terminal = ratatui::init();
+    let res = run(&mut terminal, stats);
+    ratatui::restore();
+    res
+}
+
+fn run(terminal: &mut DefaultTerminal, stats: &Stats) -> Result<()> {
+    // One tab per dimension, and the set completion last.
+    let tabs = Dimension::ALL.len() + 1;
+    let mut tab = 0;
+    loop {
+        terminal.draw(|frame| draw(frame, stats, tab))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Tab | KeyCode::Right | KeyCode::Char('l') => tab = (tab + 1) % tabs,
+                KeyCode::BackTab | KeyCode::Left | KeyCode::Char('h') => {
+                    tab = (tab + tabs - 1) % tabs
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, stats: &Stats, tab: usize) {
+    let [tabs_area, chart_area, status_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let titles = Dimension::ALL
+        .iter()
+        .map(|d| d.title())
+        .chain(["Completion"]);
+    frame.render_widget(
+        Tabs::new(titles)
+            .select(tab)
+            .highlight_style(Style::new().reversed()),
+        tabs_area,
+    );
+
+    let (title, bars): (String, Vec<Bar>) = match Dimension::ALL.get(tab) {
+        Some(&dimension) => (
+            format!(
+                " Copies by {} ({} total) ",
+                dimension.title().to_lowercase(),
+                stats.copies
+            ),
+            stats
+                .buckets(dimension)
+                .iter()
+                .map(|bucket| {
+                    Bar::default()
+                        .label(Line::from(bucket.label.clone()))
+                        .value(u64::from(bucket.copies))
+                        .text_value(format!(
+                            "{} ({})",
+                            bucket.copies,
+                            format_value(bucket.value, stats.currency)
+                        ))
+                })
+                .collect(),
+        ),
+        None => (
+            " Printings owned, per set ".to_string(),
+            stats
+                .completion
+                .iter()
+                .map(|set| {
+                    let text = match (set.size, set.percent()) {
+                        (Some(size), Some(percent)) => {
+                            format!("{}/{size} ({percent:.1}%)", set.owned)
+                        }
+                        _ => format!("{}/?", set.owned),
+                    };
+                    Bar::default()
+                        .label(Line::from(set.set.to_ascii_uppercase()))
+                        .value(set.percent().unwrap_or_default().round() as u64)
+                        .text_value(text)
+                })
+                .collect(),
+        ),
+    };
+    let mut chart = BarChart::default()
+        .direction(Direction::Horizontal)
+        .block(Block::bordered().title(title))
+        .bar_width(1)
+        .bar_gap(0)
+        .data(BarGroup::default().bars(&bars));
+    if tab == Dimension::ALL.len() {
+        chart = chart.max(100);
+    }
+    frame.render_widget(chart, chart_area);
+    frame.render_widget(Line::from(HELP_TEXT), status_area);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::CardPrices;
+    use pretty_assertions::assert_eq;
+
+    fn card(set: &str, number: &str, count: u32, rarity: &str, colors: &[&str]) -> Card {
+        Card {
+            name: format!("Card {number}"),
+            set: set.to_string(),
+            set_name: set.to_ascii_uppercase() + " Set",
+            collector_number: number.to_string(),
+            count,
+            rarity: rarity.to_string(),
+            colors: Some(colors.iter().map(|c| c.to_string()).collect()),
+            language: "en".to_string(),
+            ..Card::default()
+        }
+    }
+
+    fn priced(card: Card, usd: &str) -> Card {
+        Card {
+            prices: Some(CardPrices {
+                usd: Some(usd.parse().unwrap()),
+                usd_foil: Some(usd.parse().unwrap()),
+                ..CardPrices::default()
+            }),
+            ..card
+        }
+    }
+
+    fn summary(buckets: &[Bucket]) -> Vec<(&str, u32)> {
+        buckets
+            .iter()
+            .map(|b| (b.label.as_str(), b.copies))
+            .collect()
+    }
+
+    #[test]
+    fn test_stats() {
+        let cards = vec![
+            priced(card("blb", "1", 4, "common", &["W"]), "0.10"),
+            priced(card("blb", "2", 1, "mythic", &["U", "R"]), "12.00"),
+            Card {
+                finish: Finish::Foil,
+                ..priced(card("blb", "2", 1, "mythic", &["U", "R"]), "30.00")
+            },
+            card("m11", "149", 2, "common", &["R"]),
+            Card {
+                colors: None,
+                ..card("m11", "150", 1, "rare", &[])
+            },
+            card("m11", "151", 0, "rare", &[]),
+        ];
+        let set_sizes = BTreeMap::from([("blb".to_string(), 4)]);
+
+        let stats = stats(&cards, Currency::Usd, &set_sizes);
+        assert_eq!(stats.copies, 9);
+        assert_eq!(stats.printings, 4);
+        assert_eq!(stats.value, "42.40".parse().unwrap());
+        assert_eq!(stats.unpriced, 3);
+        assert_eq!(
+            summary(stats.buckets(Dimension::Set)),
+            vec![("BLB BLB Set", 6), ("M11 M11 Set", 3)]
+        );
+        assert_eq!(
+            summary(stats.buckets(Dimension::Rarity)),
+            vec![("Common", 6), ("Rare", 1), ("Mythic", 2)]
+        );
+        assert_eq!(
+            summary(stats.buckets(Dimension::Color)),
+            vec![("White", 4), ("Red", 2), ("Multicolor", 2), ("Unknown", 1)]
+        );
+        assert_eq!(
+            summary(stats.buckets(Dimension::Finish)),
+            vec![("Nonfoil", 8), ("Foil", 1)]
+        );
+        assert_eq!(
+            summary(stats.buckets(Dimension::Price)),
+            vec![
+                ("Under $0.25", 4),
+                ("$5.00 to $20.00", 1),
+                ("$20.00 to $100.00", 1),
+                ("No price", 3)
+            ]
+        );
+        assert_eq!(
+            stats.completion,
+            vec![
+                SetCompletion {
+                    set: "blb".to_string(),
+                    name: "BLB Set".to_string(),
+                    owned: 2,
+                    size: Some(4),
+                },
+                SetCompletion {
+                    set: "m11".to_string(),
+                    name: "M11 Set".to_string(),
+                    owned: 2,
+                    size: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_report() {
+        let cards = vec![priced(card("blb", "1", 4, "common", &["W"]), "150")];
+        let set_sizes = BTreeMap::from([("blb".to_string(), 3)]);
+        let report = format_report(&stats(&cards, Currency::Usd, &set_sizes));
+        assert!(report.starts_with("4 copies of 1 printings, worth $600.00\n\nBy set:\n"));
+        assert!(
+            report.contains("By price:\n  $100.00 and up                      4     $600.00\n")
+        );
+        assert!(
+            report
+                .contains("Set completion:\n  BLB BLB Set                           1/3   33.3%\n")
+        );
+    }
+}
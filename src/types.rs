@@ -1,5 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
 /// A Scryfall card object, reduced by quite a few fields. The API docs for the
@@ -13,20 +15,487 @@ pub struct Card {
     #[serde(default)]
     pub count: u32,
     pub colors: Option<Vec<String>>,
+    /// The colors of the mana symbols anywhere on the card, ie `["R", "W"]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub color_identity: Vec<String>,
+    /// The full type line, ie `Legendary Creature — Elf Druid`. Double-faced
+    /// cards have both faces' types, separated by `//`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub type_line: String,
+    /// Whether this card is `legal`, `not_legal`, `restricted` or `banned` in
+    /// each format, by Scryfall's format name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub legalities: BTreeMap<String, String>,
     pub rarity: String,
     pub uri: String,
     pub set: String,
-    pub foil: bool,
+    #[serde(default)]
+    pub finish: Finish,
+    /// The finishes this printing was made in, according to Scryfall. Empty
+    /// for cards looked up before this was recorded.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_finishes",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub finishes: Vec<Finish>,
+    /// Whether this printing was made in nonfoil, according to Scryfall. Only
+    /// consulted if `finishes` is empty. Scryfall's `foil` counterpart to this
+    /// ends up in `legacy_foil`, and isn't trusted, see there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonfoil: Option<bool>,
+    /// Scryfall's language code, ie `en`, `ja` or `zhs`.
+    #[serde(default = "default_language", alias = "lang")]
+    pub language: String,
+    #[serde(default)]
+    pub condition: Condition,
+    /// Which part of a deck this is in. The collection only uses the
+    /// mainboard, `import` and `add` put everything there.
+    #[serde(default, skip_serializing_if = "Section::is_mainboard")]
+    pub section: Section,
+    /// How many of `count` are sleeved in which deck, by deck name. Only kept
+    /// in the collection, see `alloc`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub allocations: BTreeMap<String, u32>,
+    /// Details Moxfield keeps about the copies in a collection, carried over
+    /// from imports and written back out on export.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purchase_price: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub alter: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub proxy: bool,
     pub prices: Option<CardPrices>,
+    /// The ids stores and clients know this printing by, for exports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtgo_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtgo_foil_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcgplayer_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cardmarket_id: Option<u64>,
+    /// Archives from before `finish` existed store a `foil` flag instead. This
+    /// is only read, see `Card::migrate`. Note that on Scryfall's card objects,
+    /// this means "is available in foil", which is why it's not trusted there.
+    #[serde(default, rename = "foil", skip_serializing)]
+    pub legacy_foil: bool,
+}
+
+fn default_language() -> String {
+    "en".to_string()
+}
+
+/// Reads Scryfall's `finishes`, skipping any this doesn't know about, ie the
+/// long gone `glossy`.
+fn deserialize_finishes<'de, D>(deserializer: D) -> Result<Vec<Finish>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let names = Vec::<String>::deserialize(deserializer)?;
+    Ok(names
+        .iter()
+        .filter_map(|name| Finish::ALL.into_iter().find(|f| f.name() == name))
+        .collect())
+}
+
+/// The finish of a physical card. Everything but `Nonfoil` is some kind of
+/// foil, and is priced as such.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Finish {
+    #[default]
+    Nonfoil,
+    Foil,
+    Etched,
+    Gilded,
+    Textured,
+    Surge,
+}
+
+impl Finish {
+    pub const ALL: [Finish; 6] = [
+        Finish::Nonfoil,
+        Finish::Foil,
+        Finish::Etched,
+        Finish::Gilded,
+        Finish::Textured,
+        Finish::Surge,
+    ];
+
+    pub fn is_foil(self) -> bool {
+        self != Finish::Nonfoil
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Finish::Nonfoil => "nonfoil",
+            Finish::Foil => "foil",
+            Finish::Etched => "etched",
+            Finish::Gilded => "gilded",
+            Finish::Textured => "textured",
+            Finish::Surge => "surge",
+        }
+    }
+
+    /// The marker Arena and Moxfield use for this finish in deck lists.
+    pub fn deck_list_marker(self) -> &'static str {
+        match self {
+            Finish::Nonfoil => "",
+            Finish::Etched => "*E*",
+            _ => "*F*",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Finish> {
+        Finish::ALL
+            .into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(s))
+    }
+}
+
+/// The condition of a physical card, in the grading most stores use.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Condition {
+    #[default]
+    #[serde(rename = "NM")]
+    NearMint,
+    #[serde(rename = "LP")]
+    LightlyPlayed,
+    #[serde(rename = "MP")]
+    ModeratelyPlayed,
+    #[serde(rename = "HP")]
+    HeavilyPlayed,
+    #[serde(rename = "DMG")]
+    Damaged,
+}
+
+impl Condition {
+    pub fn code(self) -> &'static str {
+        match self {
+            Condition::NearMint => "NM",
+            Condition::LightlyPlayed => "LP",
+            Condition::ModeratelyPlayed => "MP",
+            Condition::HeavilyPlayed => "HP",
+            Condition::Damaged => "DMG",
+        }
+    }
+
+    /// Parses either the short code (`LP`) or the spelled out name.
+    pub fn parse(s: &str) -> Option<Condition> {
+        match s.to_ascii_uppercase().as_str() {
+            "NM" | "M" | "MINT" | "NEAR MINT" => Some(Condition::NearMint),
+            "LP" | "SP" | "LIGHTLY PLAYED" | "SLIGHTLY PLAYED" => Some(Condition::LightlyPlayed),
+            "MP" | "MODERATELY PLAYED" => Some(Condition::ModeratelyPlayed),
+            "HP" | "HEAVILY PLAYED" => Some(Condition::HeavilyPlayed),
+            "DMG" | "D" | "DAMAGED" => Some(Condition::Damaged),
+            _ => None,
+        }
+    }
 }
 
-/// Small embedded struct that captures the pricing information returned by Scryfall.
+/// The parts of a deck, as deck lists separate them with headers.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Section {
+    #[default]
+    Mainboard,
+    Sideboard,
+    Commander,
+    Maybeboard,
+}
+
+impl Section {
+    /// The sections in the order deck lists have them in.
+    pub const ALL: [Section; 4] = [
+        Section::Commander,
+        Section::Mainboard,
+        Section::Sideboard,
+        Section::Maybeboard,
+    ];
+
+    pub fn is_mainboard(&self) -> bool {
+        *self == Section::Mainboard
+    }
+
+    /// The header Arena and Moxfield put above this section.
+    pub fn header(self) -> &'static str {
+        match self {
+            Section::Mainboard => "Deck",
+            Section::Sideboard => "Sideboard",
+            Section::Commander => "Commander",
+            Section::Maybeboard => "Maybeboard",
+        }
+    }
+
+    /// Parses a section header, or one of the short names `add` accepts, ie
+    /// `sb` or `cmd`.
+    pub fn parse(s: &str) -> Option<Section> {
+        match s.to_ascii_lowercase().as_str() {
+            "deck" | "main" | "mainboard" | "md" => Some(Section::Mainboard),
+            "sideboard" | "side" | "sb" => Some(Section::Sideboard),
+            "commander" | "cmd" => Some(Section::Commander),
+            "maybeboard" | "maybe" | "considering" => Some(Section::Maybeboard),
+            _ => None,
+        }
+    }
+}
+
+/// Scryfall's language codes and the names they stand for, see
+/// https://scryfall.com/docs/api/languages
+pub const LANGUAGES: [(&str, &str); 18] = [
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fr", "French"),
+    ("de", "German"),
+    ("it", "Italian"),
+    ("pt", "Portuguese"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("ru", "Russian"),
+    ("zhs", "Simplified Chinese"),
+    ("zht", "Traditional Chinese"),
+    ("he", "Hebrew"),
+    ("la", "Latin"),
+    ("grc", "Ancient Greek"),
+    ("ar", "Arabic"),
+    ("sa", "Sanskrit"),
+    ("ph", "Phyrexian"),
+    ("qya", "Quenya"),
+];
+
+/// The name of a language code, ie `Japanese` for `ja`. Unknown codes are
+/// returned as they are.
+pub fn language_name(code: &str) -> &str {
+    LANGUAGES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map_or(code, |(_, name)| name)
+}
+
+/// Turns either a language code (`ja`) or name (`Japanese`) into a code.
+pub fn language_code(language: &str) -> Option<&'static str> {
+    LANGUAGES
+        .iter()
+        .find(|(code, name)| {
+            code.eq_ignore_ascii_case(language) || name.eq_ignore_ascii_case(language)
+        })
+        .map(|(code, _)| *code)
+}
+
+impl Card {
+    /// Whether `other` is the same physical printing, ie the same card in the
+    /// same set, finish, language and condition, in the same section of a deck.
+    /// Entries in an archive are unique by this.
+    pub fn is_same_printing(&self, other: &Card) -> bool {
+        self.set == other.set
+            && self.collector_number == other.collector_number
+            && self.finish == other.finish
+            && self.language == other.language
+            && self.condition == other.condition
+            && self.section == other.section
+    }
+
+    /// How many copies are allocated to decks, all decks together.
+    pub fn allocated(&self) -> u32 {
+        self.allocations.values().sum()
+    }
+
+    /// How many copies are allocated to `deck`.
+    pub fn allocated_to(&self, deck: &str) -> u32 {
+        self.allocations.get(deck).copied().unwrap_or(0)
+    }
+
+    /// How many copies aren't allocated to any deck.
+    pub fn free(&self) -> u32 {
+        self.count.saturating_sub(self.allocated())
+    }
+
+    /// Frees allocations until no more copies are allocated than there are.
+    /// Call after lowering `count`. There's no telling which deck removed
+    /// copies came from, so decks are freed in reverse name order.
+    pub fn clamp_allocations(&mut self) {
+        let mut excess = self.allocated().saturating_sub(self.count);
+        while excess > 0
+            && let Some((deck, copies)) = self.allocations.pop_last()
+        {
+            if copies > excess {
+                self.allocations.insert(deck, copies - excess);
+            }
+            excess = excess.saturating_sub(copies);
+        }
+    }
+
+    /// Merges the copies of `other`, the same printing in all but finish or
+    /// section, into this entry, allocations included.
+    pub fn absorb(&mut self, other: &Card) {
+        self.count += other.count;
+        for (deck, copies) in &other.allocations {
+            *self.allocations.entry(deck.clone()).or_default() += copies;
+        }
+    }
+
+    /// Whether this printing was made in `finish`, or `None` if that's not
+    /// known. Scryfall only distinguishes nonfoil, foil and etched, so gilded,
+    /// textured and surge foils count as foil here.
+    pub fn is_available_in(&self, finish: Finish) -> Option<bool> {
+        if self.finishes.is_empty() {
+            return match finish {
+                Finish::Nonfoil => self.nonfoil,
+                _ => None,
+            };
+        }
+        let finish = match finish {
+            Finish::Nonfoil | Finish::Etched => finish,
+            _ => Finish::Foil,
+        };
+        Some(self.finishes.contains(&finish))
+    }
+
+    /// Carries over fields from older archive formats. Call on every card read
+    /// from an archive.
+    pub fn migrate(&mut self) {
+        if self.legacy_foil && self.finish == Finish::Nonfoil {
+            self.finish = Finish::Foil;
+        }
+        self.legacy_foil = false;
+    }
+
+    /// The USD price matching this card's finish, if Scryfall has one.
+    pub fn usd(&self) -> Option<Price> {
+        self.price(Currency::Usd)
+    }
+
+    /// The EUR price matching this card's finish, if Scryfall has one.
+    pub fn eur(&self) -> Option<Price> {
+        self.price(Currency::Eur)
+    }
+
+    /// The price in `currency` matching this card's finish, if Scryfall has
+    /// one. Scryfall leaves prices out for plenty of cards, ie digital-only
+    /// ones, brand new sets, and finishes a card was never printed in.
+    pub fn price(&self, currency: Currency) -> Option<Price> {
+        let prices = self.prices.as_ref()?;
+        let amount = match (currency, self.finish) {
+            (Currency::Usd, Finish::Nonfoil) => prices.usd,
+            (Currency::Usd, Finish::Etched) => prices.usd_etched,
+            (Currency::Usd, _) => prices.usd_foil,
+            (Currency::Eur, Finish::Nonfoil) => prices.eur,
+            (Currency::Eur, Finish::Etched) => prices.eur_etched,
+            (Currency::Eur, _) => prices.eur_foil,
+        }?;
+        Some(Price { amount, currency })
+    }
+
+    /// Whether Scryfall has a price for this card in another finish, but not
+    /// in its own. That usually means the card was never printed in this
+    /// finish, ie a mis-input.
+    pub fn lacks_finish_price(&self) -> bool {
+        let Some(prices) = &self.prices else {
+            return false;
+        };
+        let any_price = [
+            prices.usd,
+            prices.usd_foil,
+            prices.usd_etched,
+            prices.eur,
+            prices.eur_foil,
+            prices.eur_etched,
+        ]
+        .iter()
+        .any(Option::is_some);
+        any_price && self.usd().is_none() && self.eur().is_none()
+    }
+}
+
+/// One English copy of `Card {number}` from Bloomburrow, for tests.
+#[cfg(test)]
+pub fn fixture_card(number: &str) -> Card {
+    Card {
+        name: format!("Card {number}"),
+        collector_number: number.to_string(),
+        set: "blb".to_string(),
+        count: 1,
+        language: "en".to_string(),
+        ..Card::default()
+    }
+}
+
+/// `count` English copies of `name`, printed in `set` as `number`, for tests.
+/// Printings of the same name share an oracle id.
+#[cfg(test)]
+pub fn fixture_printing(name: &str, set: &str, number: &str, count: u32) -> Card {
+    Card {
+        name: name.to_string(),
+        oracle_id: format!("oracle-{name}"),
+        set: set.to_string(),
+        collector_number: number.to_string(),
+        count,
+        language: "en".to_string(),
+        ..Card::default()
+    }
+}
+
+/// Small embedded struct that captures the pricing information returned by
+/// Scryfall. Every price may be missing, Scryfall sends `null` for those.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct CardPrices {
-    pub usd: String,
-    pub usd_foil: Option<String>,
-    pub eur: String,
-    pub eur_foil: Option<String>,
+    #[serde(default)]
+    pub usd: Option<Decimal>,
+    #[serde(default)]
+    pub usd_foil: Option<Decimal>,
+    #[serde(default)]
+    pub usd_etched: Option<Decimal>,
+    #[serde(default)]
+    pub eur: Option<Decimal>,
+    #[serde(default)]
+    pub eur_foil: Option<Decimal>,
+    #[serde(default)]
+    pub eur_etched: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Currency {
+    #[default]
+    Usd,
+    Eur,
+}
+
+impl Currency {
+    pub fn code(self) -> &'static str {
+        match self {
+            Currency::Usd => "usd",
+            Currency::Eur => "eur",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Currency> {
+        match s.to_ascii_lowercase().as_str() {
+            "usd" | "$" => Some(Currency::Usd),
+            "eur" | "€" => Some(Currency::Eur),
+            _ => None,
+        }
+    }
+}
+
+/// An amount of money in some currency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Price {
+    pub amount: Decimal,
+    pub currency: Currency,
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let amount = self.amount.round_dp(2);
+        match self.currency {
+            Currency::Usd => write!(f, "${amount:.2}"),
+            Currency::Eur => write!(f, "{amount:.2}€"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -35,11 +504,85 @@ pub struct Archive(pub Vec<Card>);
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct OldArchive(pub HashMap<String, Vec<Card>>);
 
-
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct State {
-    /// A string naming a deck. This is not intended to be an absolute path as
-    /// the deck home can change, but rather the bits between home path and
-    /// `.json`, ie `/some/home/path/.config/crack/_statefile_.json`.
+    /// A string naming a deck. This is usually not an absolute path as the
+    /// deck home can change, but rather the bits between home path and
+    /// `.json`, ie `/some/home/path/.config/crack/_statefile_.json`. Lists
+    /// outside of the deck home are stored as absolute paths.
     pub currently_used_deck: Option<String>,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_null_prices() {
+        let card: Card = serde_json::from_str(
+            r#"{"name": "Digital Card", "collector_number": "1", "set_name": "Alchemy",
+                "oracle_id": "", "rarity": "rare", "uri": "", "set": "ymid",
+                "prices": {"usd": null, "usd_foil": null, "eur": null, "eur_foil": null, "tix": "0.02"}}"#,
+        )
+        .unwrap();
+        assert_eq!(card.usd(), None);
+        assert_eq!(card.eur(), None);
+        assert!(!card.lacks_finish_price());
+    }
+
+    #[test]
+    fn test_prices_follow_finish() {
+        let mut card: Card = serde_json::from_str(
+            r#"{"name": "Lightning Bolt", "collector_number": "149", "set_name": "Magic 2011",
+                "oracle_id": "", "rarity": "common", "uri": "", "set": "m11",
+                "prices": {"usd": "2.1", "usd_foil": null, "eur": "1.90", "eur_foil": null}}"#,
+        )
+        .unwrap();
+        assert_eq!(card.usd().unwrap().to_string(), "$2.10");
+        assert_eq!(card.eur().unwrap().to_string(), "1.90€");
+        assert!(!card.lacks_finish_price());
+
+        card.finish = Finish::Foil;
+        assert_eq!(card.usd(), None);
+        assert!(card.lacks_finish_price());
+    }
+
+    #[test]
+    fn test_finish_availability() {
+        let card: Card = serde_json::from_str(
+            r#"{"name": "Polliwallop", "collector_number": "189", "set_name": "Bloomburrow",
+                "oracle_id": "", "rarity": "common", "uri": "", "set": "blb",
+                "finishes": ["nonfoil", "foil", "glossy"], "foil": true, "nonfoil": true}"#,
+        )
+        .unwrap();
+        assert_eq!(card.finishes, vec![Finish::Nonfoil, Finish::Foil]);
+        assert_eq!(card.is_available_in(Finish::Nonfoil), Some(true));
+        assert_eq!(card.is_available_in(Finish::Surge), Some(true));
+        assert_eq!(card.is_available_in(Finish::Etched), Some(false));
+
+        let old = Card {
+            nonfoil: Some(false),
+            ..Card::default()
+        };
+        assert_eq!(old.is_available_in(Finish::Nonfoil), Some(false));
+        assert_eq!(old.is_available_in(Finish::Foil), None);
+    }
+
+    #[test]
+    fn test_clamp_allocations() {
+        let mut card = Card {
+            count: 5,
+            allocations: [("burn".to_string(), 2), ("prowess".to_string(), 3)].into(),
+            ..Card::default()
+        };
+        card.clamp_allocations();
+        assert_eq!(card.allocated(), 5);
+
+        card.count = 1;
+        card.clamp_allocations();
+        assert_eq!(card.allocations, [("burn".to_string(), 1)].into());
+        card.count = 0;
+        card.clamp_allocations();
+        assert!(card.allocations.is_empty());
+    }
+}
@@ -0,0 +1,301 @@
+use clap::ValueEnum;
+
+use crate::types::{Card, Section};
+
+/// The formats `validate` knows the deck construction rules of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Standard,
+    Pioneer,
+    Modern,
+    Legacy,
+    Vintage,
+    Pauper,
+    Commander,
+}
+
+impl Format {
+    /// The name Scryfall uses for this format in `legalities`.
+    fn key(self) -> &'static str {
+        match self {
+            Format::Standard => "standard",
+            Format::Pioneer => "pioneer",
+            Format::Modern => "modern",
+            Format::Legacy => "legacy",
+            Format::Vintage => "vintage",
+            Format::Pauper => "pauper",
+            Format::Commander => "commander",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Standard => "Standard",
+            Format::Pioneer => "Pioneer",
+            Format::Modern => "Modern",
+            Format::Legacy => "Legacy",
+            Format::Vintage => "Vintage",
+            Format::Pauper => "Pauper",
+            Format::Commander => "Commander",
+        }
+    }
+
+    /// How many copies of a card other than a basic land a deck may have.
+    fn max_copies(self) -> u32 {
+        match self {
+            Format::Commander => 1,
+            _ => 4,
+        }
+    }
+}
+
+const CONSTRUCTED_MIN_DECK_SIZE: u32 = 60;
+const MAX_SIDEBOARD_SIZE: u32 = 15;
+const COMMANDER_DECK_SIZE: u32 = 100;
+
+/// Checks `deck` against the deck construction rules of `format`, and returns
+/// every rule it breaks. Maybeboard cards aren't part of the deck, and are
+/// left out. In Commander, the commander counts towards the deck size, and the
+/// sideboard is ignored. Cards without legalities, ie from archives from
+/// before they were recorded, are reported as such.
+pub fn validate(deck: &[Card], format: Format) -> Vec<String> {
+    let mut violations = Vec::new();
+    let cards: Vec<&Card> = deck
+        .iter()
+        .filter(|c| {
+            !matches!(
+                (format, c.section),
+                (_, Section::Maybeboard) | (Format::Commander, Section::Sideboard)
+            )
+        })
+        .collect();
+    let count = |section: Section| -> u32 {
+        cards
+            .iter()
+            .filter(|c| c.section == section)
+            .map(|c| c.count)
+            .sum()
+    };
+
+    let main = count(Section::Mainboard) + count(Section::Commander);
+    match format {
+        Format::Commander if main != COMMANDER_DECK_SIZE => violations.push(format!(
+            "The deck has {main} cards, Commander decks have exactly {COMMANDER_DECK_SIZE}."
+        )),
+        Format::Commander => {}
+        _ if main < CONSTRUCTED_MIN_DECK_SIZE => violations.push(format!(
+            "The deck has {main} cards, {} decks need at least {CONSTRUCTED_MIN_DECK_SIZE}.",
+            format.name()
+        )),
+        _ => {}
+    }
+    let sideboard = count(Section::Sideboard);
+    if format != Format::Commander && sideboard > MAX_SIDEBOARD_SIZE {
+        violations.push(format!(
+            "The sideboard has {sideboard} cards, at most {MAX_SIDEBOARD_SIZE} are allowed."
+        ));
+    }
+
+    // Printings of the same card count together, so go by name from here on.
+    let mut by_name: Vec<(&Card, u32)> = Vec::new();
+    for card in &cards {
+        match by_name.iter_mut().find(|(c, _)| c.name == card.name) {
+            Some((_, copies)) => *copies += card.count,
+            None => by_name.push((card, card.count)),
+        }
+    }
+
+    for &(card, copies) in &by_name {
+        let legality = card.legalities.get(format.key()).map(String::as_str);
+        match legality {
+            None => violations.push(format!(
+                "{} has no legality data, look it up again to check it.",
+                card.name
+            )),
+            Some("banned") => {
+                violations.push(format!("{} is banned in {}.", card.name, format.name()))
+            }
+            Some("not_legal") => {
+                violations.push(format!("{} is not legal in {}.", card.name, format.name()))
+            }
+            Some("restricted") if copies > 1 => violations.push(format!(
+                "{} is restricted in {}, the deck has {copies} copies of it.",
+                card.name,
+                format.name()
+            )),
+            _ => {}
+        }
+
+        let max_copies = format.max_copies();
+        if copies > max_copies && !is_basic_land(card) {
+            violations.push(format!(
+                "The deck has {copies} copies of {}, {} allows {max_copies}.",
+                card.name,
+                format.name()
+            ));
+        }
+    }
+
+    if format == Format::Commander {
+        violations.extend(validate_commanders(&cards));
+    }
+    violations
+}
+
+/// Checks that there are one or two commanders, that they can be commanders,
+/// and that every card is within their color identity.
+fn validate_commanders(cards: &[&Card]) -> Vec<String> {
+    let mut violations = Vec::new();
+    let commanders: Vec<&Card> = cards
+        .iter()
+        .filter(|c| c.section == Section::Commander)
+        .copied()
+        .collect();
+    let count: u32 = commanders.iter().map(|c| c.count).sum();
+    match count {
+        0 => violations.push("The deck has no commander.".to_string()),
+        1 | 2 => {}
+        n => violations.push(format!(
+            "The deck has {n} commanders, at most 2 are allowed."
+        )),
+    }
+
+    for commander in &commanders {
+        let front = commander.type_line.split(" // ").next().unwrap_or_default();
+        if !front.contains("Legendary") || !front.contains("Creature") {
+            violations.push(format!(
+                "{} is not a legendary creature, it can't be a commander.",
+                commander.name
+            ));
+        }
+    }
+
+    let identity: Vec<&String> = commanders.iter().flat_map(|c| &c.color_identity).collect();
+    if commanders.is_empty() {
+        return violations;
+    }
+    for card in cards.iter().filter(|c| c.section != Section::Commander) {
+        let outside: Vec<&str> = card
+            .color_identity
+            .iter()
+            .filter(|color| !identity.contains(color))
+            .map(String::as_str)
+            .collect();
+        if !outside.is_empty() {
+            violations.push(format!(
+                "{} is {}, which is outside the commander's color identity.",
+                card.name,
+                outside.join("")
+            ));
+        }
+    }
+    violations
+}
+
+fn is_basic_land(card: &Card) -> bool {
+    card.type_line.starts_with("Basic ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn card(name: &str, count: u32, type_line: &str, identity: &str, legality: &str) -> Card {
+        Card {
+            name: name.to_string(),
+            count,
+            type_line: type_line.to_string(),
+            color_identity: identity.chars().map(String::from).collect(),
+            legalities: ["commander", "modern", "vintage"]
+                .into_iter()
+                .map(|format| (format.to_string(), legality.to_string()))
+                .collect(),
+            ..Card::default()
+        }
+    }
+
+    #[test]
+    fn test_constructed() {
+        let deck = vec![
+            card("Lightning Bolt", 4, "Instant", "R", "legal"),
+            card("Mountain", 52, "Basic Land — Mountain", "", "legal"),
+            card("Lightning Bolt", 1, "Instant", "R", "legal"),
+            card("Mental Misstep", 1, "Instant", "U", "banned"),
+            Card {
+                section: Section::Sideboard,
+                ..card("Smash to Smithereens", 16, "Instant", "R", "legal")
+            },
+            Card {
+                section: Section::Maybeboard,
+                ..card("Ancestral Recall", 4, "Instant", "U", "restricted")
+            },
+        ];
+        assert_eq!(
+            validate(&deck, Format::Modern),
+            vec![
+                "The deck has 58 cards, Modern decks need at least 60.",
+                "The sideboard has 16 cards, at most 15 are allowed.",
+                "The deck has 5 copies of Lightning Bolt, Modern allows 4.",
+                "Mental Misstep is banned in Modern.",
+                "The deck has 16 copies of Smash to Smithereens, Modern allows 4.",
+            ]
+        );
+        assert!(validate(&deck, Format::Pauper)[2].contains("has no legality data"));
+    }
+
+    #[test]
+    fn test_restricted() {
+        let mut deck = vec![card("Mountain", 58, "Basic Land — Mountain", "", "legal")];
+        deck.push(card("Ancestral Recall", 2, "Instant", "U", "restricted"));
+        assert_eq!(
+            validate(&deck, Format::Vintage),
+            vec!["Ancestral Recall is restricted in Vintage, the deck has 2 copies of it."]
+        );
+    }
+
+    #[test]
+    fn test_commander() {
+        let deck = vec![
+            Card {
+                section: Section::Commander,
+                ..card(
+                    "Krenko, Mob Boss",
+                    1,
+                    "Legendary Creature — Goblin Warrior",
+                    "R",
+                    "legal",
+                )
+            },
+            card("Lightning Bolt", 1, "Instant", "R", "legal"),
+            card("Counterspell", 2, "Instant", "U", "legal"),
+            card("Mountain", 96, "Basic Land — Mountain", "", "legal"),
+            Card {
+                section: Section::Sideboard,
+                ..card("Pyroblast", 1, "Instant", "R", "legal")
+            },
+        ];
+        assert_eq!(
+            validate(&deck, Format::Commander),
+            vec![
+                "The deck has 2 copies of Counterspell, Commander allows 1.",
+                "Counterspell is U, which is outside the commander's color identity.",
+            ]
+        );
+
+        let deck = vec![
+            Card {
+                section: Section::Commander,
+                ..card("Lightning Bolt", 1, "Instant", "R", "legal")
+            },
+            card("Mountain", 98, "Basic Land — Mountain", "", "legal"),
+        ];
+        assert_eq!(
+            validate(&deck, Format::Commander),
+            vec![
+                "The deck has 99 cards, Commander decks have exactly 100.",
+                "Lightning Bolt is not a legendary creature, it can't be a commander.",
+            ]
+        );
+    }
+}
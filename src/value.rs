@@ -0,0 +1,272 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Card, Currency, Price};
+
+/// The value of a collection at some point in time. `value` stores one of
+/// these next to the collection on every run, to report changes over time.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PriceSnapshot {
+    /// When the snapshot was taken, in RFC 3339.
+    pub date: String,
+    pub usd: Totals,
+    pub eur: Totals,
+    /// How many copies have no price in either currency.
+    pub unpriced: u32,
+}
+
+/// Amounts in one currency, split by finish. Etched and other special finishes
+/// count as foil.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Totals {
+    pub nonfoil: Decimal,
+    pub foil: Decimal,
+}
+
+impl Totals {
+    pub fn total(&self) -> Decimal {
+        self.nonfoil + self.foil
+    }
+
+    fn add(&mut self, card: &Card, amount: Decimal) {
+        match card.finish.is_foil() {
+            true => self.foil += amount,
+            false => self.nonfoil += amount,
+        }
+    }
+}
+
+/// A card's worth, all copies included.
+#[derive(Debug, Clone)]
+pub struct CardValue {
+    pub card: Card,
+    pub usd: Option<Price>,
+    pub eur: Option<Price>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Valuation {
+    pub snapshot: PriceSnapshot,
+    /// The most valuable cards, by the preferred currency first, the other
+    /// one second.
+    pub top: Vec<CardValue>,
+}
+
+/// Replaces the prices of every card in `cards` with those of the matching
+/// printing in `fresh`. Cards without a match keep their old prices.
+pub fn reprice(cards: &mut [Card], fresh: &[Card]) {
+    for card in cards {
+        let current = fresh
+            .iter()
+            .find(|f| f.set == card.set && f.collector_number == card.collector_number);
+        if let Some(current) = current {
+            card.prices = current.prices.clone();
+        }
+    }
+}
+
+/// Sums up the value of `cards`, and picks the `top` most valuable of them in
+/// `currency`.
+pub fn value(cards: &[Card], top: usize, currency: Currency, date: String) -> Valuation {
+    let mut snapshot = PriceSnapshot {
+        date,
+        usd: Totals::default(),
+        eur: Totals::default(),
+        unpriced: 0,
+    };
+    let mut values = Vec::new();
+    for card in cards {
+        let worth = |price: Option<Price>| {
+            price.map(|p| Price {
+                amount: p.amount * Decimal::from(card.count),
+                ..p
+            })
+        };
+        let usd = worth(card.usd());
+        let eur = worth(card.eur());
+        if let Some(usd) = usd {
+            snapshot.usd.add(card, usd.amount);
+        }
+        if let Some(eur) = eur {
+            snapshot.eur.add(card, eur.amount);
+        }
+        if usd.is_none() && eur.is_none() {
+            snapshot.unpriced += card.count;
+        }
+        values.push(CardValue {
+            card: card.clone(),
+            usd,
+            eur,
+        });
+    }
+
+    values.sort_by_key(|v| {
+        let usd = v.usd.map(|p| p.amount);
+        let eur = v.eur.map(|p| p.amount);
+        std::cmp::Reverse(match currency {
+            Currency::Usd => (usd, eur),
+            Currency::Eur => (eur, usd),
+        })
+    });
+    values.truncate(top);
+    Valuation {
+        snapshot,
+        top: values,
+    }
+}
+
+fn format_amount(amount: Decimal) -> String {
+    format!("{:.2}", amount.round_dp(2))
+}
+
+fn format_change(from: Decimal, to: Decimal) -> String {
+    match to >= from {
+        true => format!("+{}", format_amount(to - from)),
+        false => format_amount(to - from),
+    }
+}
+
+/// Formats `valuation` as a table of totals, the change since `previous` if
+/// there is one, and the most valuable cards.
+pub fn format_report(valuation: &Valuation, previous: Option<&PriceSnapshot>) -> String {
+    let PriceSnapshot { usd, eur, .. } = &valuation.snapshot;
+    let mut output = format!("{:<10}{:>12}{:>12}\n", "", "USD", "EUR");
+    for (label, usd, eur) in [
+        ("Nonfoil", usd.nonfoil, eur.nonfoil),
+        ("Foil", usd.foil, eur.foil),
+        ("Total", usd.total(), eur.total()),
+    ] {
+        output.push_str(&format!(
+            "{label:<10}{:>12}{:>12}\n",
+            format_amount(usd),
+            format_amount(eur)
+        ));
+    }
+    if let Some(previous) = previous {
+        output.push_str(&format!(
+            "{:<10}{:>12}{:>12}  (since {})\n",
+            "Change",
+            format_change(previous.usd.total(), usd.total()),
+            format_change(previous.eur.total(), eur.total()),
+            previous.date
+        ));
+    }
+    if valuation.snapshot.unpriced > 0 {
+        output.push_str(&format!(
+            "{} cards have no price.\n",
+            valuation.snapshot.unpriced
+        ));
+    }
+
+    if !valuation.top.is_empty() {
+        output.push_str(&format!("\nTop {} cards:\n", valuation.top.len()));
+    }
+    for (i, value) in valuation.top.iter().enumerate() {
+        let card = &value.card;
+        let price = |price: Option<Price>| price.map(|p| p.to_string()).unwrap_or("-".to_string());
+        let line = format!(
+            "{}x {} ({}) {} {}",
+            card.count,
+            card.name,
+            card.set.to_ascii_uppercase(),
+            card.collector_number,
+            card.finish.deck_list_marker(),
+        );
+        output.push_str(&format!(
+            "{:>3}. {:<50} {:>9} / {:>9}\n",
+            i + 1,
+            line.trim_end(),
+            price(value.usd),
+            price(value.eur),
+        ));
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{CardPrices, Finish};
+    use pretty_assertions::assert_eq;
+
+    fn card(number: &str, count: u32, finish: Finish, usd: &str, usd_foil: Option<&str>) -> Card {
+        let amount = |a: &str| a.parse::<Decimal>().unwrap();
+        Card {
+            name: format!("Card {number}"),
+            collector_number: number.to_string(),
+            set: "blb".to_string(),
+            count,
+            finish,
+            language: "en".to_string(),
+            prices: Some(CardPrices {
+                usd: Some(amount(usd)),
+                usd_foil: usd_foil.map(amount),
+                eur: Some(amount("0.10")),
+                ..CardPrices::default()
+            }),
+            ..Card::default()
+        }
+    }
+
+    #[test]
+    fn test_value() {
+        let cards = vec![
+            card("1", 4, Finish::Nonfoil, "0.25", None),
+            card("2", 1, Finish::Foil, "1.00", Some("3.50")),
+            // Neither currency has a foil price for this one.
+            card("3", 2, Finish::Foil, "1.00", None),
+        ];
+
+        let valuation = value(&cards, 2, Currency::Usd, "2025-01-01".to_string());
+        assert_eq!(
+            valuation.snapshot,
+            PriceSnapshot {
+                date: "2025-01-01".to_string(),
+                usd: Totals {
+                    nonfoil: Decimal::new(100, 2),
+                    foil: Decimal::new(350, 2),
+                },
+                eur: Totals {
+                    nonfoil: Decimal::new(40, 2),
+                    foil: Decimal::ZERO,
+                },
+                unpriced: 2,
+            }
+        );
+        let top: Vec<_> = valuation
+            .top
+            .iter()
+            .map(|v| v.card.collector_number.as_str())
+            .collect();
+        assert_eq!(top, vec!["2", "1"]);
+    }
+
+    #[test]
+    fn test_reprice() {
+        let mut cards = vec![card("1", 1, Finish::Nonfoil, "0.25", None)];
+        let fresh = vec![card("1", 1, Finish::Nonfoil, "0.30", None)];
+        reprice(&mut cards, &fresh);
+        assert_eq!(cards[0].usd().unwrap().to_string(), "$0.30");
+    }
+
+    #[test]
+    fn test_format_report_shows_change() {
+        let cards = vec![card("1", 4, Finish::Nonfoil, "0.25", None)];
+        let previous = PriceSnapshot {
+            date: "2025-01-01".to_string(),
+            usd: Totals {
+                nonfoil: Decimal::new(150, 2),
+                foil: Decimal::ZERO,
+            },
+            eur: Totals::default(),
+            unpriced: 0,
+        };
+        let report = format_report(
+            &value(&cards, 10, Currency::Usd, "now".to_string()),
+            Some(&previous),
+        );
+        assert!(report.contains("Change           -0.50       +0.40  (since 2025-01-01)"));
+        assert!(report.contains("  1. 4x Card 1 (BLB) 1"));
+        assert!(report.contains("    $1.00 /     0.40€"));
+    }
+}